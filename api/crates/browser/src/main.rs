@@ -1,5 +1,81 @@
-use browser_api::start_server;
+use browser_api::{start_server, Frontend, ServerConfig, Users};
 
-fn main() {
-    start_server(8080);
+use buckets::S3Client;
+
+use clap::Parser;
+
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Serve the artifact browser API and frontend over http."
+)]
+struct Args {
+    /// Address to listen on
+    #[arg(short, long, default_value = "127.0.0.1")]
+    address: String,
+
+    /// Port to listen on
+    #[arg(short, long, default_value = "8080")]
+    port: u16,
+
+    /// Serve the frontend from this folder, usually frontend/dist after `trunk build`
+    #[arg(short, long)]
+    frontend_dir: Option<PathBuf>,
+
+    /// Require authentication with the users listed in this JSON file
+    #[arg(short, long)]
+    users_file: Option<PathBuf>,
+
+    /// Number of requests handled at the same time
+    #[arg(short, long, default_value = "8")]
+    workers: usize,
+}
+
+#[cfg(feature = "embed-frontend")]
+fn default_frontend() -> Frontend {
+    Frontend::Embedded
+}
+
+#[cfg(not(feature = "embed-frontend"))]
+fn default_frontend() -> Frontend {
+    Frontend::None
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+    let args = Args::parse();
+    let frontend = match args.frontend_dir {
+        Some(folder) => Frontend::Directory(folder),
+        None => default_frontend(),
+    };
+    let users = match args.users_file {
+        Some(path) => Some(Users::from_file(&path)?),
+        None => None,
+    };
+    tracing::info!(
+        "Listening on http://{}:{}/, API at /api/",
+        args.address,
+        args.port
+    );
+    start_server(ServerConfig {
+        address: args.address,
+        port: args.port,
+        frontend,
+        users,
+        client: S3Client::from_env()?,
+        workers: args.workers,
+    });
+    Ok(())
 }
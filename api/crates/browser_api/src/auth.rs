@@ -0,0 +1,300 @@
+use argon2::password_hash::phc::PasswordHash;
+use argon2::password_hash::PasswordVerifier;
+use argon2::Argon2;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use serde::Deserialize;
+
+use sha2::{Digest, Sha256};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A user allowed to access the server, as listed in the users file.
+///
+/// Secrets are stored as salted argon2 hashes in the PHC format, e.g. from
+/// `echo -n secret | argon2 "$(openssl rand -base64 16)" -id -e`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct User {
+    pub name: String,
+    /// Hash of a token sent as `Authorization: Bearer <token>`
+    #[serde(default)]
+    token_hash: Option<String>,
+    /// Hash of the password for http basic auth
+    #[serde(default)]
+    password_hash: Option<String>,
+    /// Folders the user may read, everything if empty. A `/` is added to those missing it, so
+    /// that `success/master` doesn't give access to `success/master-old/`
+    #[serde(default)]
+    prefixes: Vec<String>,
+    /// Buckets the user may read, all of them if empty. `prefixes` apply in every bucket
+    #[serde(default)]
+    buckets: Vec<String>,
+}
+
+impl User {
+    pub fn can_read(&self, path: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| path.starts_with(p))
+    }
+
+    pub fn can_read_bucket(&self, bucket: &str) -> bool {
+        self.buckets.is_empty() || self.buckets.iter().any(|b| b == bucket)
+    }
+
+    /// Check the hashes and end the prefixes with `/`.
+    fn normalize(&mut self) -> Result<(), Box<dyn Error>> {
+        for hash in [&self.token_hash, &self.password_hash]
+            .into_iter()
+            .flatten()
+        {
+            PasswordHash::new(hash)
+                .map_err(|e| format!("Invalid secret hash of user {}: {}", self.name, e))?;
+        }
+        for prefix in &mut self.prefixes {
+            if !prefix.is_empty() && !prefix.ends_with('/') {
+                prefix.push('/');
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Users file given to the server, e.g.
+///
+/// ```json
+/// {
+///     "users": [
+///         { "name": "ci", "token_hash": "<hash>", "prefixes": ["success/master/"] },
+///         { "name": "qa", "token_hash": "<hash>", "buckets": ["se-ci-artifacts"] },
+///         { "name": "alice", "password_hash": "<hash>" }
+///     ]
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct Users {
+    users: Vec<User>,
+    /// Users by the SHA-256 of the credentials they were verified with, so that a token isn't
+    /// checked against the slow hash of every user on each request
+    #[serde(skip)]
+    verified: Mutex<HashMap<[u8; 32], usize>>,
+}
+
+/// Hash checked when there is no user to check a password against, made with the default cost
+/// of the `argon2` tool, so that the time to answer doesn't tell which users exist
+const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=4096,t=3,p=1$N9zq0drHiAfh4745+rptZQ$GNiCGzKJy0uIyIc2+sEXGHlzJ6KaW0kTqa4eDkNooSY";
+
+fn matches_hash(secret: &str, hash: &Option<String>) -> bool {
+    match hash {
+        Some(hash) => Argon2::default()
+            .verify_password(secret.as_bytes(), hash.as_str())
+            .is_ok(),
+        None => false,
+    }
+}
+
+impl Users {
+    pub fn from_file(path: &Path) -> Result<Users, Box<dyn Error>> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read users file {}: {}", path.display(), e))?;
+        Users::parse(&content)
+            .map_err(|e| format!("Invalid users file {}: {}", path.display(), e).into())
+    }
+
+    fn parse(content: &str) -> Result<Users, Box<dyn Error>> {
+        let mut users: Users = serde_json::from_str(content)?;
+        for user in &mut users.users {
+            user.normalize()?;
+        }
+        Ok(users)
+    }
+
+    /// The user `find` returns for the credentials, remembered once verified.
+    fn verified_user(&self, credentials: &str, find: impl Fn(&User) -> bool) -> Option<&User> {
+        let key: [u8; 32] = Sha256::digest(credentials.as_bytes()).into();
+        if let Some(&position) = self.verified.lock().unwrap().get(&key) {
+            return Some(&self.users[position]);
+        }
+        let position = self.users.iter().position(find)?;
+        self.verified.lock().unwrap().insert(key, position);
+        Some(&self.users[position])
+    }
+
+    /// Find the user matching the `Authorization` header of a request.
+    pub fn authenticate(&self, authorization: Option<&str>) -> Option<&User> {
+        let (scheme, credentials) = authorization?.trim().split_once(' ')?;
+        let credentials = credentials.trim();
+        if scheme.eq_ignore_ascii_case("bearer") {
+            return self.verified_user(&format!("bearer {}", credentials), |user| {
+                matches_hash(credentials, &user.token_hash)
+            });
+        }
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = STANDARD.decode(credentials).ok()?;
+            let decoded = String::from_utf8(decoded).ok()?;
+            let (name, password) = decoded.split_once(':')?;
+            let user = self.verified_user(&format!("basic {}", decoded), |user| {
+                user.name == name && matches_hash(password, &user.password_hash)
+            });
+            let checked = self
+                .users
+                .iter()
+                .any(|user| user.name == name && user.password_hash.is_some());
+            if !checked {
+                matches_hash(password, &Some(DUMMY_HASH.to_string()));
+            }
+            return user;
+        }
+        None
+    }
+}
+
+/// Check if the requester may read a path. Without a user, authentication is disabled.
+pub fn can_read(user: Option<&User>, path: &str) -> bool {
+    match user {
+        Some(user) => user.can_read(path),
+        None => true,
+    }
+}
+
+/// Check if the requester may read a bucket. Without a user, authentication is disabled.
+pub fn can_read_bucket(user: Option<&User>, bucket: &str) -> bool {
+    match user {
+        Some(user) => user.can_read_bucket(bucket),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use argon2::password_hash::PasswordHasher;
+    use argon2::{Algorithm, Params, Version};
+
+    /// Hash with the smallest cost, the cost is read back from the hash when verifying
+    fn hash(secret: &str) -> String {
+        let params = Params::new(8, 1, 1, None).unwrap();
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password(secret.as_bytes())
+            .unwrap()
+            .to_string()
+    }
+
+    fn users() -> Users {
+        Users::parse(
+            &serde_json::json!({
+                "users": [
+                    {
+                        "name": "ci",
+                        "token_hash": hash("ci-token"),
+                        "prefixes": ["success/master", "success/release/"]
+                    },
+                    {
+                        "name": "qa",
+                        "token_hash": hash("qa-token"),
+                        "buckets": ["se-ci-artifacts"]
+                    },
+                    { "name": "alice", "password_hash": hash("pass:word") }
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap()
+    }
+
+    fn basic(credentials: &str) -> String {
+        format!("Basic {}", STANDARD.encode(credentials))
+    }
+
+    fn name(user: Option<&User>) -> Option<&str> {
+        user.map(|user| user.name.as_str())
+    }
+
+    #[test]
+    fn authenticate_with_token() {
+        let users = users();
+        assert_eq!(
+            name(users.authenticate(Some("Bearer qa-token"))),
+            Some("qa")
+        );
+        // Remembered after the first check
+        assert_eq!(
+            name(users.authenticate(Some("bearer  qa-token "))),
+            Some("qa")
+        );
+        assert_eq!(
+            name(users.authenticate(Some("Bearer ci-token"))),
+            Some("ci")
+        );
+        assert_eq!(name(users.authenticate(Some("Bearer other"))), None);
+        assert_eq!(name(users.authenticate(Some("Bearer"))), None);
+    }
+
+    #[test]
+    fn authenticate_with_password() {
+        let users = users();
+        let alice = basic("alice:pass:word");
+        assert_eq!(name(users.authenticate(Some(&alice))), Some("alice"));
+        assert_eq!(name(users.authenticate(Some(&alice))), Some("alice"));
+        assert_eq!(name(users.authenticate(Some(&basic("alice:pass")))), None);
+        assert_eq!(
+            name(users.authenticate(Some(&basic("bob:pass:word")))),
+            None
+        );
+        // A token is not a password
+        assert_eq!(name(users.authenticate(Some(&basic("ci:ci-token")))), None);
+        assert_eq!(name(users.authenticate(Some("Basic !!!"))), None);
+    }
+
+    #[test]
+    fn dummy_hash_is_valid() {
+        assert!(PasswordHash::new(DUMMY_HASH).is_ok());
+        assert!(!matches_hash("", &Some(DUMMY_HASH.to_string())));
+    }
+
+    #[test]
+    fn unknown_schemes_are_refused() {
+        let users = users();
+        assert_eq!(name(users.authenticate(None)), None);
+        assert_eq!(name(users.authenticate(Some("Digest qa-token"))), None);
+    }
+
+    #[test]
+    fn prefixes_are_folders() {
+        let users = users();
+        let ci = users.authenticate(Some("Bearer ci-token")).unwrap();
+        assert!(ci.can_read("success/master/sdk/a.deb"));
+        assert!(ci.can_read("success/release/sdk/"));
+        assert!(!ci.can_read("success/master-old/sdk/a.deb"));
+        assert!(!ci.can_read("success/master"));
+        assert!(!ci.can_read("success/other/"));
+        let alice = users.authenticate(Some(&basic("alice:pass:word")));
+        assert!(can_read(alice, "anything"));
+        assert!(can_read(None, "anything"));
+    }
+
+    #[test]
+    fn buckets_are_limited() {
+        let users = users();
+        let qa = users.authenticate(Some("Bearer qa-token"));
+        assert!(can_read_bucket(qa, "se-ci-artifacts"));
+        assert!(!can_read_bucket(qa, "other"));
+        let ci = users.authenticate(Some("Bearer ci-token"));
+        assert!(can_read_bucket(ci, "other"));
+        assert!(can_read_bucket(None, "other"));
+    }
+
+    #[test]
+    fn unsalted_and_invalid_hashes_are_refused() {
+        let sha256 = r#"{"users": [{"name": "ci", "token_sha256": "abcd"}]}"#;
+        assert!(Users::parse(sha256).is_err());
+        let invalid = r#"{"users": [{"name": "ci", "token_hash": "abcd"}]}"#;
+        assert!(Users::parse(invalid).is_err());
+    }
+}
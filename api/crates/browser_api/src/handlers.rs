@@ -0,0 +1,612 @@
+use buckets::{
+    find_latest_build, list_branches, platform_folder, search_objects, LatestBuild, S3Client,
+    PLATFORMS,
+};
+
+use crate::auth::{can_read, can_read_bucket, User};
+use crate::metrics::Metrics;
+
+use s3::error::S3Error;
+use s3::serde_types::Object;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tiny_http::{Header, Response, ResponseBox, StatusCode};
+
+pub type ApiResponse = ResponseBox;
+
+#[derive(Serialize)]
+struct ArtifactEntry {
+    key: String,
+    size: u64,
+    last_modified: String,
+}
+
+impl From<Object> for ArtifactEntry {
+    fn from(file: Object) -> Self {
+        ArtifactEntry {
+            key: file.key,
+            size: file.size,
+            last_modified: file.last_modified,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PlatformFolder {
+    os: String,
+    architecture: String,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct LatestBuildEntry {
+    branch: String,
+    timestamp: String,
+    commit_hash: String,
+    path: String,
+    platforms: Vec<PlatformFolder>,
+}
+
+impl From<LatestBuild> for LatestBuildEntry {
+    fn from(build: LatestBuild) -> Self {
+        let platforms = PLATFORMS
+            .iter()
+            .filter_map(|(os, architecture)| {
+                let folder = platform_folder(os, architecture).ok()?;
+                Some(PlatformFolder {
+                    os: os.to_string(),
+                    architecture: architecture.to_string(),
+                    path: format!("{}{}", build.path, folder),
+                })
+            })
+            .collect();
+        LatestBuildEntry {
+            branch: build.branch,
+            timestamp: build.timestamp,
+            commit_hash: build.commit_hash,
+            path: build.path,
+            platforms,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ShareLink {
+    url: String,
+    expiry_secs: u32,
+}
+
+#[derive(Serialize)]
+struct ErrorMessage {
+    error: String,
+}
+
+fn header(name: &str, value: &str) -> Result<Header, Box<dyn Error>> {
+    Header::from_bytes(name, value)
+        .map_err(|_| format!("Invalid value for the {} header: {:?}", name, value).into())
+}
+
+/// Add headers to a response, answering with a 500 instead if one of them can't be sent, e.g.
+/// because it's made from a key that isn't ASCII.
+fn with_headers(mut response: ApiResponse, headers: &[(&str, &str)]) -> ApiResponse {
+    for (name, value) in headers {
+        match header(name, value) {
+            Ok(header) => response.add_header(header),
+            Err(e) => return error_response(&e.to_string(), 500),
+        }
+    }
+    response
+}
+
+/// Characters left as they are in an RFC 5987 `filename*`, the `attr-char`s
+const FILENAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// `Content-Disposition` of an attachment, with the name in UTF-8 for browsers that read
+/// `filename*` and an ASCII approximation for the others.
+fn attachment_disposition(file_name: &str) -> String {
+    let ascii_name: String = file_name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "_".to_string(),
+        })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii_name,
+        utf8_percent_encode(file_name, FILENAME_ENCODE_SET)
+    )
+}
+
+fn json_response<T: Serialize>(value: &T, status: u16) -> ApiResponse {
+    let body = serde_json::to_vec(value).unwrap();
+    with_headers(
+        Response::from_data(body)
+            .with_status_code(StatusCode(status))
+            .boxed(),
+        &[
+            ("Content-Type", "application/json"),
+            ("Access-Control-Allow-Origin", "*"),
+        ],
+    )
+}
+
+pub fn error_response(message: &str, status: u16) -> ApiResponse {
+    json_response(
+        &ErrorMessage {
+            error: message.to_string(),
+        },
+        status,
+    )
+}
+
+fn required_param<'a>(
+    params: &'a HashMap<String, String>,
+    name: &str,
+) -> Result<&'a str, ApiResponse> {
+    match params.get(name) {
+        Some(value) => Ok(value.as_str()),
+        None => Err(error_response(
+            &format!("Missing query parameter '{}'", name),
+            400,
+        )),
+    }
+}
+
+pub fn unauthorized() -> ApiResponse {
+    with_headers(
+        error_response("Authentication required", 401),
+        &[(
+            "WWW-Authenticate",
+            "Basic realm=\"brows3rs\", charset=\"UTF-8\"",
+        )],
+    )
+}
+
+/// Forward "not found" and "access denied" from the bucket, anything else is our problem.
+fn error_status(error: &(dyn Error + 'static)) -> u16 {
+    match error.downcast_ref::<S3Error>() {
+        Some(S3Error::Http(status @ (403 | 404), _)) => *status,
+        _ => 500,
+    }
+}
+
+fn result_response<T: Serialize>(result: Result<T, Box<dyn Error>>) -> ApiResponse {
+    match result {
+        Ok(value) => json_response(&value, 200),
+        Err(e) => s3_error_response(e),
+    }
+}
+
+/// `GET /list?prefix=` - every file below the prefix.
+pub fn list(client: &S3Client, params: &HashMap<String, String>) -> ApiResponse {
+    let prefix = match required_param(params, "prefix") {
+        Ok(prefix) => prefix,
+        Err(response) => return response,
+    };
+    let entries = client
+        .list_all_objects_sync(prefix)
+        .map(|objects| -> Vec<ArtifactEntry> {
+            objects
+                .into_iter()
+                .flat_map(|object| object.contents)
+                .map(ArtifactEntry::from)
+                .collect()
+        });
+    result_response(entries)
+}
+
+/// `GET /search?prefix=&q=` - files below the prefix matching a name fragment or glob.
+pub fn search(client: &S3Client, params: &HashMap<String, String>) -> ApiResponse {
+    let (prefix, query) = match (
+        required_param(params, "prefix"),
+        required_param(params, "q"),
+    ) {
+        (Ok(prefix), Ok(query)) => (prefix, query),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let entries = search_objects(client, prefix, query).map(|files| {
+        files
+            .into_iter()
+            .map(ArtifactEntry::from)
+            .collect::<Vec<_>>()
+    });
+    result_response(entries)
+}
+
+/// Newest build of every branch, leaving out branches whose build can't be told.
+fn latest_builds_of_all_branches(client: &S3Client) -> Result<Vec<LatestBuild>, Box<dyn Error>> {
+    let mut builds = Vec::new();
+    for branch in list_branches(client)? {
+        match find_latest_build(client, &branch) {
+            Ok(build) => builds.extend(build),
+            // Don't let one broken branch hide the builds of all others
+            Err(e) => tracing::warn!("Skipping branch {}: {}", branch, e),
+        }
+    }
+    Ok(builds)
+}
+
+/// How long the builds of every branch are reused, as finding them lists every branch
+const LATEST_BUILDS_TTL: Duration = Duration::from_secs(30);
+
+/// Newest build of every branch by bucket, shared by the requests to `/latest` for
+/// [`LATEST_BUILDS_TTL`].
+#[derive(Default)]
+pub struct LatestBuildsCache {
+    builds: Mutex<HashMap<String, (Instant, Vec<LatestBuild>)>>,
+}
+
+impl LatestBuildsCache {
+    /// The cached builds of the bucket of `client`, found again once they are too old. The lock
+    /// is held while finding them, so that concurrent requests wait for one scan of the bucket
+    /// instead of each making their own.
+    fn get(&self, client: &S3Client) -> Result<Vec<LatestBuild>, Box<dyn Error>> {
+        let mut cache = self.builds.lock().unwrap();
+        if let Some((found, builds)) = cache.get(client.bucket_name()) {
+            if found.elapsed() < LATEST_BUILDS_TTL {
+                return Ok(builds.clone());
+            }
+        }
+        let builds = latest_builds_of_all_branches(client)?;
+        cache.insert(
+            client.bucket_name().to_string(),
+            (Instant::now(), builds.clone()),
+        );
+        Ok(builds)
+    }
+}
+
+/// `GET /latest?branch=` - newest successful commit of a branch, or of every branch if
+/// `branch` is left out. Builds the user can't read are left out.
+pub fn latest(
+    client: &S3Client,
+    params: &HashMap<String, String>,
+    user: Option<&User>,
+    cache: &LatestBuildsCache,
+) -> ApiResponse {
+    let builds = match params.get("branch") {
+        Some(branch) => find_latest_build(client, branch).map(Vec::from_iter),
+        None => cache.get(client),
+    };
+    let builds = builds.map(|builds| {
+        builds
+            .into_iter()
+            .filter(|build| can_read(user, &build.path))
+            .map(LatestBuildEntry::from)
+            .collect::<Vec<_>>()
+    });
+    result_response(builds)
+}
+
+/// Response with bytes `start` to `end` (inclusive) of a file of `size` bytes, or the whole file
+/// without a range, streamed from the bucket as it's sent.
+fn streamed_object(
+    client: &S3Client,
+    key: &str,
+    range: Option<(u64, u64)>,
+    size: u64,
+    status: u16,
+) -> ApiResponse {
+    let length = match range {
+        Some((start, end)) => end - start + 1,
+        None => size,
+    };
+    Response::new(
+        StatusCode(status),
+        Vec::new(),
+        client.object_reader(key, range, size),
+        Some(length as usize),
+        None,
+    )
+    // Send the length rather than chunks, so that browsers can show the download progress
+    .with_chunked_threshold(usize::MAX)
+    .boxed()
+}
+
+/// `GET /download?key=` - content of a single file as an attachment.
+pub fn download(client: &S3Client, params: &HashMap<String, String>) -> ApiResponse {
+    let key = match required_param(params, "key") {
+        Ok(key) => key,
+        Err(response) => return response,
+    };
+    match client.object_size_sync(key) {
+        Ok(size) => {
+            let file_name = key.rsplit('/').next().unwrap();
+            with_headers(
+                streamed_object(client, key, None, size, 200),
+                &[
+                    ("Content-Type", "application/octet-stream"),
+                    ("Content-Disposition", &attachment_disposition(file_name)),
+                    ("Access-Control-Allow-Origin", "*"),
+                ],
+            )
+        }
+        Err(e) => s3_error_response(e),
+    }
+}
+
+/// `GET /buckets` - buckets on the S3 host that the user can read, to pass as `bucket` to the
+/// other endpoints.
+pub fn buckets(client: &S3Client, user: Option<&User>) -> ApiResponse {
+    let buckets = client.list_buckets_sync().map(|buckets| {
+        buckets
+            .into_iter()
+            .filter(|bucket| can_read_bucket(user, bucket))
+            .collect::<Vec<_>>()
+    });
+    result_response(buckets)
+}
+
+/// Presigned urls are valid for a day unless asked otherwise
+const DEFAULT_SHARE_EXPIRY_SECS: u32 = 24 * 60 * 60;
+/// S3 doesn't accept presigned urls valid for more than a week
+const MAX_SHARE_EXPIRY_SECS: u32 = 7 * 24 * 60 * 60;
+
+/// `GET /share?key=&expiry=` - presigned url to download a file without credentials, valid for
+/// `expiry` seconds.
+pub fn share(client: &S3Client, params: &HashMap<String, String>) -> ApiResponse {
+    let key = match required_param(params, "key") {
+        Ok(key) => key,
+        Err(response) => return response,
+    };
+    let expiry_secs = match params.get("expiry").map(|expiry| expiry.parse::<u32>()) {
+        None => DEFAULT_SHARE_EXPIRY_SECS,
+        Some(Ok(expiry_secs)) if (1..=MAX_SHARE_EXPIRY_SECS).contains(&expiry_secs) => expiry_secs,
+        _ => {
+            return error_response(
+                &format!("Expiry must be 1 to {} seconds", MAX_SHARE_EXPIRY_SECS),
+                400,
+            )
+        }
+    };
+    let link = client
+        .presign_get(key, expiry_secs)
+        .map(|url| ShareLink { url, expiry_secs });
+    result_response(link)
+}
+
+/// Resolve a `Range: bytes=...` header into an inclusive byte range of a file of `size` bytes.
+/// Returns `None` if the range can't be satisfied.
+fn resolve_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let (start, end) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+    // Several ranges would need a multipart response
+    if size == 0 || end.contains(',') {
+        return None;
+    }
+    // A missing bound is `None`, an invalid one makes the whole range invalid
+    let bound = |value: &str| match value {
+        "" => Some(None),
+        value => value.parse::<u64>().ok().map(Some),
+    };
+    let (start, end) = match (bound(start)?, bound(end)?) {
+        (Some(start), Some(end)) => (start, end.min(size - 1)),
+        (Some(start), None) => (start, size - 1),
+        (None, Some(suffix)) => (size.saturating_sub(suffix), size - 1),
+        (None, None) => return None,
+    };
+    if start > end {
+        return None;
+    }
+    Some((start, end))
+}
+
+fn s3_error_response(error: Box<dyn Error>) -> ApiResponse {
+    error_response(&error.to_string(), error_status(error.as_ref()))
+}
+
+/// Part of a file asked for with `Range`, or the error response to send instead.
+fn object_range_response(
+    client: &S3Client,
+    key: &str,
+    range: &str,
+) -> Result<ApiResponse, ApiResponse> {
+    let size = client.object_size_sync(key).map_err(s3_error_response)?;
+    let (start, end) = match resolve_range(range, size) {
+        Some(range) => range,
+        None => {
+            return Err(with_headers(
+                error_response("Requested range not satisfiable", 416),
+                &[
+                    ("Content-Range", &format!("bytes */{}", size)),
+                    ("Access-Control-Expose-Headers", "Content-Range"),
+                ],
+            ))
+        }
+    };
+    Ok(with_headers(
+        streamed_object(client, key, Some((start, end)), size, 206),
+        &[(
+            "Content-Range",
+            &format!("bytes {}-{}/{}", start, end, size),
+        )],
+    ))
+}
+
+/// `GET /object?key=` - content of a single file with its `Content-Type`, for previews.
+/// Supports a single `Range` so that the end of large logs can be fetched on its own.
+pub fn object(
+    client: &S3Client,
+    params: &HashMap<String, String>,
+    range: Option<&str>,
+) -> ApiResponse {
+    let key = match required_param(params, "key") {
+        Ok(key) => key,
+        Err(response) => return response,
+    };
+    let response = match range {
+        Some(range) => object_range_response(client, key, range),
+        None => client
+            .object_size_sync(key)
+            .map(|size| streamed_object(client, key, None, size, 200))
+            .map_err(s3_error_response),
+    };
+    // Errors keep their own JSON content type
+    let response = match response {
+        Ok(response) => response,
+        Err(response) => return response,
+    };
+    let content_type = mime_guess::from_path(key).first_or_octet_stream();
+    with_headers(
+        response,
+        &[
+            ("Content-Type", content_type.as_ref()),
+            // Artifacts are served from the origin of the app, so an html or svg file opened on
+            // its own mustn't run scripts as the logged in user
+            ("X-Content-Type-Options", "nosniff"),
+            ("Content-Security-Policy", "sandbox"),
+            ("Accept-Ranges", "bytes"),
+            ("Access-Control-Allow-Origin", "*"),
+            ("Access-Control-Expose-Headers", "Content-Range"),
+        ],
+    )
+}
+
+/// `GET /metrics` - request and S3 call metrics for Prometheus.
+pub fn metrics_response(metrics: &Metrics) -> ApiResponse {
+    with_headers(
+        Response::from_string(metrics.render()).boxed(),
+        &[("Content-Type", "text/plain; version=0.0.4")],
+    )
+}
+
+#[derive(Serialize)]
+struct Status {
+    status: &'static str,
+}
+
+/// `GET /healthz` - the server is running.
+pub fn healthz() -> ApiResponse {
+    json_response(&Status { status: "ok" }, 200)
+}
+
+/// `GET /readyz` - the bucket can be listed, 503 with the error otherwise.
+pub fn readyz(client: &S3Client) -> ApiResponse {
+    match client.check_bucket_sync() {
+        Ok(()) => json_response(&Status { status: "ready" }, 200),
+        Err(e) => error_response(&e.to_string(), 503),
+    }
+}
+
+/// Answer CORS preflight requests, which browsers send before requests with a suffix `Range`.
+pub fn preflight() -> ApiResponse {
+    with_headers(
+        Response::from_data(Vec::new())
+            .with_status_code(StatusCode(204))
+            .boxed(),
+        &[
+            ("Access-Control-Allow-Origin", "*"),
+            ("Access-Control-Allow-Methods", "GET"),
+            ("Access-Control-Allow-Headers", "Range"),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attachment_disposition_of_ascii_name() {
+        assert_eq!(
+            attachment_disposition("sdk-2.13.deb"),
+            "attachment; filename=\"sdk-2.13.deb\"; filename*=UTF-8''sdk-2.13.deb"
+        );
+    }
+
+    #[test]
+    fn attachment_disposition_escapes_quotes() {
+        assert_eq!(
+            attachment_disposition("a \"b\".txt"),
+            "attachment; filename=\"a \\\"b\\\".txt\"; filename*=UTF-8''a%20%22b%22.txt"
+        );
+    }
+
+    #[test]
+    fn attachment_disposition_of_non_ascii_name_is_a_valid_header() {
+        let disposition = attachment_disposition("rapport-æøå.pdf");
+        assert_eq!(
+            disposition,
+            "attachment; filename=\"rapport-___.pdf\"; filename*=UTF-8''rapport-%C3%A6%C3%B8%C3%A5.pdf"
+        );
+        assert!(header("Content-Disposition", &disposition).is_ok());
+    }
+
+    #[test]
+    fn range_with_both_bounds() {
+        assert_eq!(resolve_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(resolve_range("bytes=5-5", 1000), Some((5, 5)));
+        assert_eq!(resolve_range(" bytes=0-0 ", 1), Some((0, 0)));
+    }
+
+    #[test]
+    fn suffix_range_is_the_end_of_the_file() {
+        assert_eq!(resolve_range("bytes=-100", 1000), Some((900, 999)));
+        // A suffix longer than the file is the whole file
+        assert_eq!(resolve_range("bytes=-5000", 1000), Some((0, 999)));
+        assert_eq!(resolve_range("bytes=-0", 1000), None);
+    }
+
+    #[test]
+    fn open_ended_range_goes_to_the_end_of_the_file() {
+        assert_eq!(resolve_range("bytes=100-", 1000), Some((100, 999)));
+        assert_eq!(resolve_range("bytes=999-", 1000), Some((999, 999)));
+    }
+
+    #[test]
+    fn end_past_the_file_is_clamped() {
+        assert_eq!(resolve_range("bytes=900-5000", 1000), Some((900, 999)));
+    }
+
+    #[test]
+    fn start_past_the_file_is_not_satisfiable() {
+        assert_eq!(resolve_range("bytes=1000-", 1000), None);
+        assert_eq!(resolve_range("bytes=1000-1100", 1000), None);
+        assert_eq!(resolve_range("bytes=10-5", 1000), None);
+    }
+
+    #[test]
+    fn empty_file_is_not_satisfiable() {
+        assert_eq!(resolve_range("bytes=0-", 0), None);
+        assert_eq!(resolve_range("bytes=-10", 0), None);
+        assert_eq!(resolve_range("bytes=0-0", 0), None);
+    }
+
+    #[test]
+    fn multiple_and_malformed_ranges_are_refused() {
+        assert_eq!(resolve_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(resolve_range("bytes=0-,-5", 1000), None);
+        assert_eq!(resolve_range("bytes=-", 1000), None);
+        assert_eq!(resolve_range("bytes=a-5", 1000), None);
+        assert_eq!(resolve_range("bytes=0-x", 1000), None);
+        assert_eq!(resolve_range("lines=0-5", 1000), None);
+        assert_eq!(resolve_range("bytes=5", 1000), None);
+    }
+
+    #[test]
+    fn invalid_header_becomes_server_error() {
+        let response = with_headers(Response::from_data(Vec::new()).boxed(), &[("X-Key", "æ\n")]);
+        assert_eq!(response.status_code(), StatusCode(500));
+    }
+}
@@ -1,4 +1,11 @@
+mod auth;
 mod endpoint;
+mod handlers;
+mod metrics;
+mod static_files;
 
 // Export functions from library and other modules within this library from here
+pub use crate::auth::Users;
 pub use crate::endpoint::start_server;
+pub use crate::endpoint::ServerConfig;
+pub use crate::static_files::Frontend;
@@ -0,0 +1,96 @@
+use buckets::{s3_call_stats, LATENCY_BUCKETS};
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Counters of the served requests, rendered in the Prometheus text format by `/metrics`.
+#[derive(Default)]
+pub struct Metrics {
+    /// Requests by endpoint and status code
+    requests: Mutex<BTreeMap<(&'static str, u16), u64>>,
+    bytes_served: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record(&self, endpoint: &'static str, status: u16, bytes: u64) {
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry((endpoint, status))
+            .or_default() += 1;
+        self.bytes_served.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str("# HELP brows3rs_requests_total Served http requests.\n");
+        output.push_str("# TYPE brows3rs_requests_total counter\n");
+        for ((endpoint, status), count) in self.requests.lock().unwrap().iter() {
+            writeln!(
+                output,
+                "brows3rs_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}",
+                endpoint, status, count
+            )
+            .unwrap();
+        }
+
+        output.push_str("# HELP brows3rs_served_bytes_total Bytes sent in response bodies.\n");
+        output.push_str("# TYPE brows3rs_served_bytes_total counter\n");
+        writeln!(
+            output,
+            "brows3rs_served_bytes_total {}",
+            self.bytes_served.load(Ordering::Relaxed)
+        )
+        .unwrap();
+
+        output.push_str("# HELP brows3rs_s3_call_duration_seconds Latency of S3 calls.\n");
+        output.push_str("# TYPE brows3rs_s3_call_duration_seconds histogram\n");
+        let s3_calls = s3_call_stats();
+        for (operation, stats) in &s3_calls {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(stats.buckets.iter()) {
+                cumulative += count;
+                writeln!(
+                    output,
+                    "brows3rs_s3_call_duration_seconds_bucket{{operation=\"{}\",le=\"{}\"}} {}",
+                    operation, bound, cumulative
+                )
+                .unwrap();
+            }
+            writeln!(
+                output,
+                "brows3rs_s3_call_duration_seconds_bucket{{operation=\"{}\",le=\"+Inf\"}} {}",
+                operation, stats.count
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "brows3rs_s3_call_duration_seconds_sum{{operation=\"{}\"}} {}",
+                operation, stats.sum_secs
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "brows3rs_s3_call_duration_seconds_count{{operation=\"{}\"}} {}",
+                operation, stats.count
+            )
+            .unwrap();
+        }
+
+        output.push_str("# HELP brows3rs_s3_call_errors_total Failed S3 calls.\n");
+        output.push_str("# TYPE brows3rs_s3_call_errors_total counter\n");
+        for (operation, stats) in &s3_calls {
+            writeln!(
+                output,
+                "brows3rs_s3_call_errors_total{{operation=\"{}\"}} {}",
+                operation, stats.errors
+            )
+            .unwrap();
+        }
+        output
+    }
+}
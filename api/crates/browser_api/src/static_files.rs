@@ -0,0 +1,131 @@
+use crate::handlers::ApiResponse;
+
+use std::path::{Component, Path, PathBuf};
+
+use tiny_http::{Header, Response};
+
+#[cfg(feature = "embed-frontend")]
+static EMBEDDED_FRONTEND: include_dir::Dir<'_> =
+    include_dir::include_dir!("$CARGO_MANIFEST_DIR/../../../frontend/dist");
+
+/// Where the built frontend (the `dist` folder created by `trunk build`) is served from.
+pub enum Frontend {
+    /// Only serve the API
+    None,
+    /// Serve files from a folder on disk
+    Directory(PathBuf),
+    /// Serve the files embedded into the binary at build time
+    #[cfg(feature = "embed-frontend")]
+    Embedded,
+}
+
+impl Frontend {
+    fn read(&self, relative_path: &str) -> Option<Vec<u8>> {
+        match self {
+            Frontend::None => None,
+            Frontend::Directory(root) => {
+                let path = Path::new(relative_path);
+                // Don't let requests escape the frontend folder
+                if path
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_)))
+                {
+                    return None;
+                }
+                std::fs::read(root.join(path)).ok()
+            }
+            #[cfg(feature = "embed-frontend")]
+            Frontend::Embedded => EMBEDDED_FRONTEND
+                .get_file(relative_path)
+                .map(|file| file.contents().to_vec()),
+        }
+    }
+}
+
+/// Serve a file of the frontend, `/` being its `index.html`.
+pub fn static_file(frontend: &Frontend, url_path: &str) -> Option<ApiResponse> {
+    let relative_path = match url_path.trim_start_matches('/') {
+        "" => "index.html",
+        relative_path => relative_path,
+    };
+    let content = frontend.read(relative_path)?;
+    let content_type = mime_guess::from_path(relative_path).first_or_octet_stream();
+    Some(
+        Response::from_data(content)
+            .with_header(Header::from_bytes("Content-Type", content_type.as_ref()).unwrap())
+            .boxed(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    use tempfile::TempDir;
+
+    /// A frontend in `dist` with a secret file next to it.
+    fn frontend() -> (TempDir, Frontend) {
+        let folder = TempDir::new().unwrap();
+        let dist = folder.path().join("dist");
+        std::fs::create_dir_all(dist.join("assets")).unwrap();
+        std::fs::write(dist.join("index.html"), "<html>").unwrap();
+        std::fs::write(dist.join("assets").join("app.js"), "app").unwrap();
+        std::fs::write(folder.path().join("secret.txt"), "secret").unwrap();
+        (folder, Frontend::Directory(dist))
+    }
+
+    fn body(response: ApiResponse) -> String {
+        let mut body = String::new();
+        response.into_reader().read_to_string(&mut body).unwrap();
+        body
+    }
+
+    #[test]
+    fn serves_index_and_files() {
+        let (_folder, frontend) = frontend();
+        assert_eq!(body(static_file(&frontend, "/").unwrap()), "<html>");
+        assert_eq!(
+            body(static_file(&frontend, "/assets/app.js").unwrap()),
+            "app"
+        );
+        assert!(static_file(&frontend, "/missing.js").is_none());
+    }
+
+    #[test]
+    fn parent_folders_are_refused() {
+        let (_folder, frontend) = frontend();
+        for path in [
+            "/../secret.txt",
+            "/assets/../../secret.txt",
+            "/assets/../index.html",
+            "/./index.html",
+        ] {
+            assert!(static_file(&frontend, path).is_none(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn absolute_paths_stay_in_the_frontend() {
+        let (folder, frontend) = frontend();
+        let secret = folder.path().join("secret.txt");
+        // Leading slashes are stripped, leaving a path below the frontend folder
+        let url_path = format!("//{}", secret.display());
+        assert!(static_file(&frontend, &url_path).is_none());
+        assert!(frontend.read(&secret.display().to_string()).is_none());
+    }
+
+    #[test]
+    fn encoded_separators_are_not_decoded() {
+        let (_folder, frontend) = frontend();
+        for path in ["/..%2fsecret.txt", "/%2e%2e/secret.txt", "/..%5csecret.txt"] {
+            assert!(static_file(&frontend, path).is_none(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn no_frontend_serves_nothing() {
+        assert!(static_file(&Frontend::None, "/").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::client::S3Client;
+
+use hmac::Mac;
+
+use http::header::{AUTHORIZATION, HOST};
+use http::HeaderMap;
+
+use s3::error::S3Error;
+use s3::signing::{self, HmacSha256};
+
+use serde::Deserialize;
+
+use std::error::Error;
+
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+use url::Url;
+
+/// Hash of the empty body of a `GET`
+const EMPTY_PAYLOAD_SHA256: &str =
+    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[derive(Deserialize)]
+struct ListAllMyBucketsResult {
+    #[serde(rename = "Buckets")]
+    buckets: BucketList,
+}
+
+#[derive(Deserialize)]
+struct BucketList {
+    #[serde(rename = "Bucket", default)]
+    buckets: Vec<BucketName>,
+}
+
+#[derive(Deserialize)]
+struct BucketName {
+    #[serde(rename = "Name")]
+    name: String,
+}
+
+impl S3Client {
+    /// Sign and send a single `ListBuckets` request, returning the body of the response.
+    ///
+    /// rust-s3 only talks to a single bucket, so the request is signed here.
+    async fn request_bucket_list(&self) -> Result<String, S3Error> {
+        let region = self.bucket().region();
+        let access_key = &self.config().access_key;
+        let secret_key = &self.config().secret_key;
+        let url = Url::parse(&format!("{}://{}/", region.scheme(), region.host()))?;
+
+        let now = OffsetDateTime::now_utc();
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, region.host().parse()?);
+        headers.insert(
+            "x-amz-date",
+            now.format(format_description!(
+                "[year][month][day]T[hour][minute][second]Z"
+            ))?
+            .parse()?,
+        );
+        headers.insert("x-amz-content-sha256", EMPTY_PAYLOAD_SHA256.parse()?);
+        let canonical_request =
+            signing::canonical_request("GET", &url, &headers, EMPTY_PAYLOAD_SHA256)?;
+        let string_to_sign = signing::string_to_sign(&now, &region, &canonical_request)?;
+        let mut hmac =
+            HmacSha256::new_from_slice(&signing::signing_key(&now, secret_key, &region, "s3")?)?;
+        hmac.update(string_to_sign.as_bytes());
+        let signature = hex::encode(hmac.finalize().into_bytes());
+        let authorization = signing::authorization_header(
+            access_key,
+            &now,
+            &region,
+            &signing::signed_header_string(&headers),
+            &signature,
+        )?;
+        headers.insert(AUTHORIZATION, authorization.parse()?);
+
+        let response = reqwest::Client::new()
+            .get(url)
+            .headers(headers)
+            .send()
+            .await?;
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+        // Fail like the calls made by rust-s3, so that the status can be told apart
+        if !(200..300).contains(&status) {
+            return Err(S3Error::Http(status, body));
+        }
+        Ok(body)
+    }
+
+    /// Names of every bucket the credentials can see.
+    pub async fn list_buckets(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        // Signed again for every attempt, as the signature includes the time
+        let host = self.bucket().region().host();
+        let body = self
+            .retried("list_buckets", &host, || self.request_bucket_list())
+            .await?;
+        let result: ListAllMyBucketsResult = quick_xml::de::from_str(&body)
+            .map_err(|e| format!("Invalid ListBuckets response: {}", e))?;
+        Ok(result
+            .buckets
+            .buckets
+            .into_iter()
+            .map(|bucket| bucket.name)
+            .collect())
+    }
+
+    pub fn list_buckets_sync(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        self.block_on(self.list_buckets())
+    }
+}
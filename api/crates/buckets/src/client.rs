@@ -0,0 +1,142 @@
+use crate::retry::RetryPolicy;
+use crate::stats::timed;
+
+use cli::{read_credentials_with, S3Config};
+
+use s3::bucket::Bucket;
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::region::Region;
+
+use std::error::Error;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+use tokio::runtime::Runtime;
+
+/// Connection to a bucket, made once and shared by every call.
+///
+/// The async methods are for callers with their own runtime. The `_sync` ones run them on a
+/// runtime owned by the client, created on first use, so that a whole listing or download
+/// shares one runtime. They must not be called from async code. Clients made with
+/// [`S3Client::for_bucket`] share the runtime and configuration of the original.
+///
+/// Listings and reads of objects are retried as set by the [`RetryPolicy`] of the client.
+pub struct S3Client {
+    config: S3Config,
+    bucket: Bucket,
+    runtime: Arc<OnceLock<Runtime>>,
+    retry_policy: RetryPolicy,
+}
+
+fn open_bucket(config: &S3Config) -> Result<Bucket, Box<dyn Error>> {
+    // The hostname may come with a scheme, e.g. from a https URL given to the downloader
+    let endpoint = if config.hostname.contains("://") {
+        config.hostname.clone()
+    } else {
+        format!("http://{}", config.hostname)
+    };
+    let region = Region::Custom {
+        region: "".to_owned(),
+        endpoint,
+    };
+    let credentials = Credentials {
+        access_key: Some(config.access_key.clone()),
+        secret_key: Some(config.secret_key.clone()),
+        security_token: None,
+        session_token: None,
+        expiration: None,
+    };
+    let bucket = Bucket::new(&config.bucket_name, region, credentials)?.with_path_style();
+    Ok(bucket)
+}
+
+impl S3Client {
+    pub fn new(config: S3Config) -> Result<S3Client, Box<dyn Error>> {
+        Ok(S3Client {
+            bucket: open_bucket(&config)?,
+            config,
+            runtime: Arc::new(OnceLock::new()),
+            retry_policy: RetryPolicy::default(),
+        })
+    }
+
+    /// Client for the host, credentials and bucket in `S3_HOSTNAME`, `S3_ACCESSKEY`,
+    /// `S3_SECRETKEY` and `S3_BUCKET`, asking for the missing ones, retrying as set in the
+    /// `S3_RETRY_*` variables.
+    pub fn from_env() -> Result<S3Client, Box<dyn Error>> {
+        S3Client::from_env_for(None, None)
+    }
+
+    /// Like [`S3Client::from_env`], but for the given host and bucket when set, instead of
+    /// `S3_HOSTNAME` and `S3_BUCKET`.
+    pub fn from_env_for(
+        hostname: Option<&str>,
+        bucket_name: Option<&str>,
+    ) -> Result<S3Client, Box<dyn Error>> {
+        let retry_policy = RetryPolicy::from_env()?;
+        let config = read_credentials_with(
+            hostname.map(str::to_string),
+            bucket_name.map(str::to_string),
+        );
+        Ok(S3Client::new(config)?.with_retry_policy(retry_policy))
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> S3Client {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Client for another bucket on the same host, with the same credentials.
+    pub fn for_bucket(&self, bucket_name: &str) -> Result<S3Client, Box<dyn Error>> {
+        let config = S3Config {
+            hostname: self.config.hostname.clone(),
+            access_key: self.config.access_key.clone(),
+            secret_key: self.config.secret_key.clone(),
+            bucket_name: bucket_name.to_string(),
+        };
+        Ok(S3Client {
+            bucket: open_bucket(&config)?,
+            config,
+            runtime: self.runtime.clone(),
+            retry_policy: self.retry_policy.clone(),
+        })
+    }
+
+    pub fn bucket_name(&self) -> &str {
+        &self.config.bucket_name
+    }
+
+    pub(crate) fn config(&self) -> &S3Config {
+        &self.config
+    }
+
+    pub(crate) fn bucket(&self) -> &Bucket {
+        &self.bucket
+    }
+
+    /// Make an S3 call on `key`, recording every attempt and retrying as set by the policy.
+    pub(crate) async fn retried<T, F, Fut>(
+        &self,
+        operation: &'static str,
+        key: &str,
+        mut call: F,
+    ) -> Result<T, S3Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, S3Error>>,
+    {
+        self.retry_policy
+            .run(operation, key, || timed(operation, call()))
+            .await
+    }
+
+    pub(crate) fn runtime(&self) -> &Runtime {
+        self.runtime.get_or_init(|| Runtime::new().unwrap())
+    }
+
+    /// Run an async method to completion on the runtime of the client.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime().block_on(future)
+    }
+}
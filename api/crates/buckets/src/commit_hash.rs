@@ -0,0 +1,182 @@
+use crate::latest::last_segment;
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Shortest abbreviation of a commit hash accepted, as git does
+const MIN_LENGTH: usize = 4;
+
+/// Why looking up the artifacts of a commit hash failed.
+#[derive(Debug)]
+pub enum CommitHashError {
+    /// The given hash isn't a hexadecimal (abbreviated) commit hash
+    Invalid(String),
+    /// No commit folder matches the hash
+    NotFound(String),
+    /// The hash is a prefix of several different commits
+    Ambiguous {
+        commit_hash: String,
+        /// Matching commit folders
+        candidates: Vec<String>,
+    },
+}
+
+impl Display for CommitHashError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommitHashError::Invalid(commit_hash) => write!(
+                f,
+                "{} is not a commit hash, expected at least {} hexadecimal digits",
+                commit_hash, MIN_LENGTH
+            ),
+            CommitHashError::NotFound(commit_hash) => {
+                write!(
+                    f,
+                    "Did not find any artifact with commit hash {}",
+                    commit_hash
+                )
+            }
+            CommitHashError::Ambiguous {
+                commit_hash,
+                candidates,
+            } => {
+                write!(f, "Commit hash {} is ambiguous, it matches:", commit_hash)?;
+                for candidate in candidates {
+                    write!(f, "\n  {}", candidate)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for CommitHashError {}
+
+fn is_hex(hash: &str) -> bool {
+    !hash.is_empty() && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+pub(crate) fn validate_commit_hash(commit_hash: &str) -> Result<(), CommitHashError> {
+    if commit_hash.len() < MIN_LENGTH || !is_hex(commit_hash) {
+        return Err(CommitHashError::Invalid(commit_hash.to_string()));
+    }
+    Ok(())
+}
+
+/// Check if a hash is a complete SHA-1 or SHA-256 commit hash rather than an abbreviation.
+pub(crate) fn is_full_commit_hash(commit_hash: &str) -> bool {
+    matches!(commit_hash.len(), 40 | 64) && is_hex(commit_hash)
+}
+
+/// Check if the name of a commit folder is the given commit, where either may be abbreviated.
+pub fn matches_commit_hash(folder_hash: &str, commit_hash: &str) -> bool {
+    let folder_hash = folder_hash.to_ascii_lowercase();
+    let commit_hash = commit_hash.to_ascii_lowercase();
+    is_hex(&folder_hash)
+        && (folder_hash.starts_with(&commit_hash) || commit_hash.starts_with(&folder_hash))
+}
+
+/// Check that the matching commit folders all belong to the same commit.
+pub(crate) fn check_unique_commit(
+    commit_hash: &str,
+    candidates: &[String],
+) -> Result<(), CommitHashError> {
+    let commits: BTreeSet<String> = candidates
+        .iter()
+        .map(|path| last_segment(path).to_ascii_lowercase())
+        .collect();
+    match commits.len() {
+        0 => Err(CommitHashError::NotFound(commit_hash.to_string())),
+        1 => Ok(()),
+        _ => Err(CommitHashError::Ambiguous {
+            commit_hash: commit_hash.to_string(),
+            candidates: candidates.to_vec(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL_SHA1: &str = "2bfbaee6a3f5c1d8e9b0a7c6d5e4f3a2b1c0d9e8";
+
+    fn folders(hashes: &[&str]) -> Vec<String> {
+        hashes
+            .iter()
+            .map(|hash| format!("success/master/sdk/commit/20240501-1000/{}/", hash))
+            .collect()
+    }
+
+    #[test]
+    fn too_short_or_non_hex_hashes_are_invalid() {
+        for commit_hash in ["", "2bf", "2bfg", "master", "2bfb aee6", "-2bfb"] {
+            assert!(
+                matches!(
+                    validate_commit_hash(commit_hash),
+                    Err(CommitHashError::Invalid(_))
+                ),
+                "{}",
+                commit_hash
+            );
+        }
+        assert!(validate_commit_hash(&FULL_SHA1[..MIN_LENGTH]).is_ok());
+        assert!(validate_commit_hash("2BFBAEE6").is_ok());
+        assert!(validate_commit_hash(FULL_SHA1).is_ok());
+    }
+
+    #[test]
+    fn full_hashes() {
+        assert!(is_full_commit_hash(FULL_SHA1));
+        assert!(is_full_commit_hash(&"ab".repeat(32)));
+        assert!(!is_full_commit_hash(&FULL_SHA1[..39]));
+        assert!(!is_full_commit_hash("2bfbaee6"));
+        assert!(!is_full_commit_hash(&"xy".repeat(20)));
+    }
+
+    #[test]
+    fn either_hash_may_be_abbreviated() {
+        assert!(matches_commit_hash("2bfbaee6", FULL_SHA1));
+        assert!(matches_commit_hash(FULL_SHA1, "2bfbaee6"));
+        assert!(matches_commit_hash("2BFBAEE6", "2bfb"));
+        assert!(matches_commit_hash(FULL_SHA1, FULL_SHA1));
+        assert!(!matches_commit_hash("2bfbaee7", FULL_SHA1));
+        assert!(!matches_commit_hash("2bfc", "2bfbaee6"));
+        // Folders that aren't commits
+        assert!(!matches_commit_hash("latest", "2bfb"));
+        assert!(!matches_commit_hash("", "2bfb"));
+    }
+
+    #[test]
+    fn one_commit_is_unique() {
+        // The same commit built twice
+        assert!(check_unique_commit("2bfb", &folders(&["2bfbaee6", "2BFBAEE6"])).is_ok());
+    }
+
+    #[test]
+    fn no_commit_is_not_found() {
+        assert!(matches!(
+            check_unique_commit("2bfb", &[]),
+            Err(CommitHashError::NotFound(hash)) if hash == "2bfb"
+        ));
+    }
+
+    #[test]
+    fn several_commits_are_ambiguous() {
+        let candidates = folders(&["2bfbaee6", "2bfb1111"]);
+        let error = check_unique_commit("2bfb", &candidates).unwrap_err();
+        assert!(matches!(
+            &error,
+            CommitHashError::Ambiguous { commit_hash, candidates: found }
+                if commit_hash == "2bfb" && *found == candidates
+        ));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Commit hash 2bfb is ambiguous, it matches:\n  {}\n  {}",
+                candidates[0], candidates[1]
+            )
+        );
+    }
+}
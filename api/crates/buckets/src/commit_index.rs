@@ -0,0 +1,446 @@
+use crate::client::S3Client;
+use crate::commit_hash::{
+    check_unique_commit, is_full_commit_hash, matches_commit_hash, validate_commit_hash,
+};
+use crate::latest::{branch_commit_folder, last_segment};
+use crate::s3buckets::{
+    branch_timestamp_folders, find_artifact_with_commit_hash,
+    find_commit_artifacts_in_all_branches, scan_timestamp_folders, CommitArtifact, CommitMatch,
+    SCAN_CONCURRENCY,
+};
+use crate::timestamp::parse_timestamp;
+
+use futures::stream::{self, StreamExt};
+
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Where a commit index is stored.
+pub enum IndexLocation {
+    /// A local file
+    File(PathBuf),
+    /// A key in the bucket
+    Bucket(String),
+}
+
+/// Commit folders of every branch, to find the artifacts of a commit without scanning the
+/// bucket. Built by [`build_commit_index`] and stored as JSON.
+#[derive(Serialize, Deserialize, Default)]
+pub struct CommitIndex {
+    commits: Vec<CommitArtifact>,
+}
+
+impl CommitIndex {
+    pub async fn load(
+        client: &S3Client,
+        location: &IndexLocation,
+    ) -> Result<CommitIndex, Box<dyn Error>> {
+        let content = match location {
+            IndexLocation::File(path) => std::fs::read(path)
+                .map_err(|e| format!("Failed to read index {}: {}", path.display(), e))?,
+            IndexLocation::Bucket(key) => client.get_object(key).await?,
+        };
+        let index =
+            serde_json::from_slice(&content).map_err(|e| format!("Invalid commit index: {}", e))?;
+        Ok(index)
+    }
+
+    pub async fn save(
+        &self,
+        client: &S3Client,
+        location: &IndexLocation,
+    ) -> Result<(), Box<dyn Error>> {
+        let content = serde_json::to_vec_pretty(self)?;
+        match location {
+            IndexLocation::File(path) => std::fs::write(path, content)
+                .map_err(|e| format!("Failed to write index {}: {}", path.display(), e))?,
+            IndexLocation::Bucket(key) => client.put_object(key, &content).await?,
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.commits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commits.is_empty()
+    }
+
+    /// Split the timestamp folders listed in the bucket into the commit folders the index knows
+    /// in them, as `(tag, timestamp folder, commit folder)`, and the folders it doesn't know,
+    /// which were made after it and must be listed. Indexed folders that are no longer listed are
+    /// left out.
+    fn split_known<T: Clone>(
+        &self,
+        timestamp_folders: Vec<(T, String)>,
+    ) -> (Vec<CommitMatch<T>>, Vec<(T, String)>) {
+        let listed: HashMap<&str, &T> = timestamp_folders
+            .iter()
+            .map(|(tag, folder)| (folder.as_str(), tag))
+            .collect();
+        let known = self
+            .commits
+            .iter()
+            .filter_map(|commit| {
+                let folder = timestamp_folder(&commit.path);
+                let tag = listed.get(folder)?;
+                Some(((*tag).clone(), folder.to_string(), commit.path.clone()))
+            })
+            .collect();
+        let indexed: HashSet<&str> = self
+            .commits
+            .iter()
+            .map(|commit| timestamp_folder(&commit.path))
+            .collect();
+        let unknown = timestamp_folders
+            .into_iter()
+            .filter(|(_, folder)| !indexed.contains(folder.as_str()))
+            .collect();
+        (known, unknown)
+    }
+
+    /// Like [`CommitIndex::split_known`], but only the commit folders matching a commit hash.
+    fn split_by_index<T: Clone>(
+        &self,
+        commit_hash: &str,
+        timestamp_folders: Vec<(T, String)>,
+    ) -> (Vec<CommitMatch<T>>, Vec<(T, String)>) {
+        let (known, unknown) = self.split_known(timestamp_folders);
+        let found = known
+            .into_iter()
+            .filter(|(_, _, path)| matches_commit_hash(last_segment(path), commit_hash))
+            .collect();
+        (found, unknown)
+    }
+
+    /// Commit folders of a commit hash, which may be abbreviated, newest first, looked up in the
+    /// index alone. Only complete for an index of the current folders, like the one of
+    /// [`list_commit_folders`].
+    pub fn lookup(&self, commit_hash: &str) -> Result<Vec<CommitArtifact>, Box<dyn Error>> {
+        validate_commit_hash(commit_hash)?;
+        let found = self
+            .commits
+            .iter()
+            .filter(|commit| matches_commit_hash(&commit.commit_hash, commit_hash))
+            .map(|commit| {
+                let folder = timestamp_folder(&commit.path).to_string();
+                (commit.branch.clone(), folder, commit.path.clone())
+            })
+            .collect();
+        Ok(newest_unique(commit_hash, found)?
+            .into_iter()
+            .map(CommitArtifact::from)
+            .collect())
+    }
+}
+
+/// Timestamp folder of a commit folder, both ending with `/`.
+fn timestamp_folder(commit_folder: &str) -> &str {
+    let commit_hash = last_segment(commit_folder);
+    let end = commit_folder.trim_end_matches('/').len() - commit_hash.len();
+    &commit_folder[..end]
+}
+
+/// Order matches as `(tag, timestamp folder, commit folder)` newest first and check that they
+/// are all the same commit.
+fn newest_unique<T>(
+    commit_hash: &str,
+    mut found: Vec<CommitMatch<T>>,
+) -> Result<Vec<CommitMatch<T>>, Box<dyn Error>> {
+    found.sort_by_cached_key(|(_, folder, _)| {
+        let timestamp = last_segment(folder);
+        Reverse((parse_timestamp(timestamp), timestamp.to_string()))
+    });
+    let candidates: Vec<String> = found.iter().map(|(_, _, path)| path.clone()).collect();
+    check_unique_commit(commit_hash, &candidates)?;
+    Ok(found)
+}
+
+/// List the commit folders in timestamp folders tagged with their branch, `SCAN_CONCURRENCY`
+/// timestamp folders at a time.
+async fn list_commits_in(
+    client: &S3Client,
+    timestamp_folders: Vec<(String, String)>,
+) -> Result<Vec<CommitArtifact>, Box<dyn Error>> {
+    let mut listings = stream::iter(timestamp_folders)
+        .map(|(branch_name, timestamp_folder)| async move {
+            let commit_folders = client.list_folders(&timestamp_folder).await;
+            (branch_name, timestamp_folder, commit_folders)
+        })
+        .buffered(SCAN_CONCURRENCY);
+    let mut commits = Vec::new();
+    while let Some((branch_name, timestamp_folder, commit_folders)) = listings.next().await {
+        for path in commit_folders? {
+            commits.push(CommitArtifact::from((
+                branch_name.clone(),
+                timestamp_folder.clone(),
+                path,
+            )));
+        }
+    }
+    Ok(commits)
+}
+
+/// List the commit folders of every branch.
+pub async fn build_commit_index(client: &S3Client) -> Result<CommitIndex, Box<dyn Error>> {
+    let timestamp_folders = branch_timestamp_folders(client).await?;
+    Ok(CommitIndex {
+        commits: list_commits_in(client, timestamp_folders).await?,
+    })
+}
+
+/// Index of the current commit folders of a branch, or of every branch, to look up many commits
+/// with [`CommitIndex::lookup`] after listing the bucket once. Only the timestamp folders that
+/// `index` doesn't know are listed.
+pub async fn list_commit_folders(
+    client: &S3Client,
+    index: Option<&CommitIndex>,
+    branch_name: Option<&str>,
+) -> Result<CommitIndex, Box<dyn Error>> {
+    let timestamp_folders = match branch_name {
+        Some(branch_name) => client
+            .list_folders(&branch_commit_folder(branch_name))
+            .await?
+            .into_iter()
+            .map(|folder| (branch_name.to_string(), folder))
+            .collect(),
+        None => branch_timestamp_folders(client).await?,
+    };
+    let (known, unknown) = match index {
+        Some(index) => index.split_known(timestamp_folders),
+        None => (Vec::new(), timestamp_folders),
+    };
+    let mut commits: Vec<CommitArtifact> = known.into_iter().map(CommitArtifact::from).collect();
+    commits.extend(list_commits_in(client, unknown).await?);
+    Ok(CommitIndex { commits })
+}
+
+/// Like [`find_artifact_with_commit_hash`], but looking in the index first. The timestamp
+/// folders below `prefix` are still listed, and the ones the index doesn't know, because they
+/// were made after it, are scanned, so that a stale index doesn't hide newer builds.
+pub async fn lookup_artifact_with_commit_hash(
+    client: &S3Client,
+    index: Option<&CommitIndex>,
+    prefix: &str,
+    commit_hash: &str,
+) -> Result<String, Box<dyn Error>> {
+    validate_commit_hash(commit_hash)?;
+    let Some(index) = index else {
+        return find_artifact_with_commit_hash(client, prefix, commit_hash).await;
+    };
+    let timestamp_folders = client
+        .list_folders(prefix)
+        .await?
+        .into_iter()
+        .map(|folder| ((), folder))
+        .collect();
+    let (mut found, unindexed) = index.split_by_index(commit_hash, timestamp_folders);
+    let stop_at_first = is_full_commit_hash(commit_hash);
+    found.extend(scan_timestamp_folders(client, unindexed, commit_hash, stop_at_first).await?);
+    let (_, _, path) = newest_unique(commit_hash, found)?.swap_remove(0);
+    Ok(path)
+}
+
+/// Like [`find_commit_artifacts_in_all_branches`], but looking in the index first. Like for
+/// [`lookup_artifact_with_commit_hash`], the timestamp folders the index doesn't know are scanned.
+pub async fn lookup_commit_artifacts_in_all_branches(
+    client: &S3Client,
+    index: Option<&CommitIndex>,
+    commit_hash: &str,
+) -> Result<Vec<CommitArtifact>, Box<dyn Error>> {
+    validate_commit_hash(commit_hash)?;
+    let Some(index) = index else {
+        return find_commit_artifacts_in_all_branches(client, commit_hash).await;
+    };
+    let timestamp_folders = branch_timestamp_folders(client).await?;
+    let (mut found, unindexed) = index.split_by_index(commit_hash, timestamp_folders);
+    found.extend(scan_timestamp_folders(client, unindexed, commit_hash, false).await?);
+    Ok(newest_unique(commit_hash, found)?
+        .into_iter()
+        .map(CommitArtifact::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::commit_hash::CommitHashError;
+
+    const MASTER: &str = "success/master/sdk/commit/";
+    const FULL_HASH: &str = "aaaa1111bbbb2222cccc3333dddd4444eeee5555";
+
+    fn artifact(timestamp: &str, commit_hash: &str) -> CommitArtifact {
+        CommitArtifact {
+            branch: "master".to_string(),
+            timestamp: timestamp.to_string(),
+            commit_hash: commit_hash.to_string(),
+            path: format!("{}{}/{}/", MASTER, timestamp, commit_hash),
+        }
+    }
+
+    fn index() -> CommitIndex {
+        CommitIndex {
+            commits: vec![
+                artifact("2024-05-01T10:00:00Z", FULL_HASH),
+                artifact("2024-05-02T10:00:00Z", FULL_HASH),
+                artifact("2024-05-02T10:00:00Z", "aaaa2222"),
+                artifact("2024-05-03T10:00:00Z", "aaab2222"),
+                artifact("2024-05-04T10:00:00Z", "cccc3333"),
+            ],
+        }
+    }
+
+    fn listed(timestamps: &[&str]) -> Vec<((), String)> {
+        timestamps
+            .iter()
+            .map(|timestamp| ((), format!("{}{}/", MASTER, timestamp)))
+            .collect()
+    }
+
+    fn lookup(commit_hash: &str, timestamps: &[&str]) -> Result<Vec<String>, Box<dyn Error>> {
+        let (found, unindexed) = index().split_by_index(commit_hash, listed(timestamps));
+        assert!(unindexed.is_empty());
+        Ok(newest_unique(commit_hash, found)?
+            .into_iter()
+            .map(|(_, _, path)| path)
+            .collect())
+    }
+
+    const ALL: &[&str] = &[
+        "2024-05-01T10:00:00Z",
+        "2024-05-02T10:00:00Z",
+        "2024-05-03T10:00:00Z",
+        "2024-05-04T10:00:00Z",
+    ];
+
+    #[test]
+    fn timestamp_folder_of_commit_folder() {
+        assert_eq!(
+            timestamp_folder("success/master/sdk/commit/20240501-1000/aaaa1111/"),
+            "success/master/sdk/commit/20240501-1000/"
+        );
+    }
+
+    #[test]
+    fn lookup_full_hash_finds_every_build_newest_first() {
+        assert_eq!(
+            lookup(FULL_HASH, ALL).unwrap(),
+            vec![
+                format!("{}2024-05-02T10:00:00Z/{}/", MASTER, FULL_HASH),
+                format!("{}2024-05-01T10:00:00Z/{}/", MASTER, FULL_HASH),
+            ]
+        );
+    }
+
+    #[test]
+    fn lookup_abbreviated_hash() {
+        assert_eq!(
+            lookup("cccc33", ALL).unwrap(),
+            vec![format!("{}2024-05-04T10:00:00Z/cccc3333/", MASTER)]
+        );
+    }
+
+    #[test]
+    fn lookup_ambiguous_hash() {
+        let error = lookup("aaaa", ALL).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CommitHashError>(),
+            Some(CommitHashError::Ambiguous { candidates, .. }) if candidates.len() == 3
+        ));
+    }
+
+    #[test]
+    fn lookup_missing_hash() {
+        let error = lookup("dddd", ALL).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<CommitHashError>(),
+            Some(CommitHashError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn folders_made_after_the_index_are_scanned() {
+        let mut timestamps = ALL.to_vec();
+        timestamps.push("2024-06-01T10:00:00Z");
+        let (found, unindexed) = index().split_by_index("cccc", listed(&timestamps));
+        assert_eq!(found.len(), 1);
+        assert_eq!(unindexed, listed(&["2024-06-01T10:00:00Z"]));
+    }
+
+    #[test]
+    fn lookup_in_the_index_alone() {
+        let index = index();
+        let paths = |commit_hash| -> Vec<String> {
+            let artifacts = index.lookup(commit_hash).unwrap();
+            artifacts
+                .into_iter()
+                .map(|artifact| artifact.path)
+                .collect()
+        };
+        assert_eq!(
+            paths(&FULL_HASH[..8]),
+            vec![
+                format!("{}2024-05-02T10:00:00Z/{}/", MASTER, FULL_HASH),
+                format!("{}2024-05-01T10:00:00Z/{}/", MASTER, FULL_HASH),
+            ]
+        );
+        assert_eq!(
+            paths("aaab"),
+            vec![format!("{}2024-05-03T10:00:00Z/aaab2222/", MASTER)]
+        );
+        for (commit_hash, expected) in [
+            ("aaaa", "ambiguous"),
+            ("dddd", "Did not find"),
+            ("xy", "not a commit"),
+        ] {
+            let error = index.lookup(commit_hash).err().unwrap().to_string();
+            assert!(error.contains(expected), "{}", error);
+        }
+    }
+
+    #[test]
+    fn known_commits_are_tagged_with_their_listed_branch() {
+        let timestamps = vec![
+            (
+                "master".to_string(),
+                format!("{}2024-05-04T10:00:00Z/", MASTER),
+            ),
+            (
+                "master".to_string(),
+                format!("{}2024-06-01T10:00:00Z/", MASTER),
+            ),
+        ];
+        let (known, unknown) = index().split_known(timestamps);
+        assert_eq!(
+            known,
+            vec![(
+                "master".to_string(),
+                format!("{}2024-05-04T10:00:00Z/", MASTER),
+                format!("{}2024-05-04T10:00:00Z/cccc3333/", MASTER)
+            )]
+        );
+        assert_eq!(
+            unknown,
+            vec![(
+                "master".to_string(),
+                format!("{}2024-06-01T10:00:00Z/", MASTER)
+            )]
+        );
+    }
+
+    #[test]
+    fn folders_no_longer_listed_are_ignored() {
+        assert_eq!(
+            lookup("aaaa", &["2024-05-01T10:00:00Z"]).unwrap(),
+            vec![format!("{}2024-05-01T10:00:00Z/{}/", MASTER, FULL_HASH)]
+        );
+        let (found, unindexed) = index().split_by_index("cccc", Vec::<((), String)>::new());
+        assert!(found.is_empty() && unindexed.is_empty());
+    }
+}
@@ -0,0 +1,340 @@
+use flate2::read::GzDecoder;
+
+use xz2::read::XzDecoder;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+enum ArchiveKind {
+    Zip,
+    TarGz,
+    TarXz,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+        Some(ArchiveKind::TarXz)
+    } else {
+        None
+    }
+}
+
+/// Refuse to write to `relative_path` below `destination` through a symbolic link, which may
+/// point outside of it, e.g. one unpacked there from another archive before.
+fn check_no_symlinks(
+    archive: &Path,
+    destination: &Path,
+    relative_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut path = destination.to_path_buf();
+    for component in relative_path.components() {
+        path.push(component);
+        if path
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        {
+            return Err(format!(
+                "Refusing to extract {}, {} is a symbolic link",
+                archive.display(),
+                path.display()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Path of an archive entry, as long as it stays inside the folder it's extracted to.
+fn safe_entry_path(archive: &Path, entry: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    if entry
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Ok(entry.to_path_buf());
+    }
+    Err(format!(
+        "Refusing to extract {}, it contains the unsafe path {}",
+        archive.display(),
+        entry.display()
+    )
+    .into())
+}
+
+fn extract_zip(archive: &Path, destination: &Path) -> Result<(), Box<dyn Error>> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let relative_path = entry.enclosed_name().ok_or_else(|| {
+            format!(
+                "Refusing to extract {}, it contains the unsafe path {}",
+                archive.display(),
+                entry.name()
+            )
+        })?;
+        check_no_symlinks(archive, destination, &relative_path)?;
+        let path = destination.join(relative_path);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut entry, &mut File::create(&path)?)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            // Only the permissions, no setuid, setgid or sticky bits
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode & 0o777))?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_tar<R: Read>(
+    archive: &Path,
+    reader: R,
+    destination: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_path_buf();
+        safe_entry_path(archive, &entry_path)?;
+        check_no_symlinks(archive, destination, &entry_path)?;
+        // Also refuses to write through links pointing outside of the destination
+        if !entry.unpack_in(destination)? {
+            return Err(format!(
+                "Refusing to extract {}, {} would end up outside of {}",
+                archive.display(),
+                entry_path.display(),
+                destination.display()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Extract a `.zip`, `.tar.gz` or `.tar.xz` archive into `destination`. Archives with entries
+/// that would end up outside of `destination`, like `../file`, `/file` or a file below a
+/// symbolic link, are refused.
+pub fn extract_archive(archive: &Path, destination: &Path) -> Result<(), Box<dyn Error>> {
+    let file = File::open(archive)?;
+    match archive_kind(archive) {
+        Some(ArchiveKind::Zip) => extract_zip(archive, destination),
+        Some(ArchiveKind::TarGz) => extract_tar(archive, GzDecoder::new(file), destination),
+        Some(ArchiveKind::TarXz) => extract_tar(archive, XzDecoder::new(file), destination),
+        None => Err(format!("{} is not a supported archive", archive.display()).into()),
+    }
+}
+
+/// Extract the archives among `files` next to them, deleting them afterwards if asked to. Other
+/// files are left alone. Returns the extracted archives.
+pub fn extract_archives(
+    files: &[PathBuf],
+    delete_archives: bool,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut archives: Vec<PathBuf> = files
+        .iter()
+        .filter(|file| archive_kind(file).is_some())
+        .cloned()
+        .collect();
+    archives.sort();
+    for archive in &archives {
+        println!("Extracting {}", archive.display());
+        extract_archive(archive, archive.parent().unwrap())?;
+        if delete_archives {
+            std::fs::remove_file(archive)?;
+        }
+    }
+    Ok(archives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use std::io::Write;
+
+    use tempfile::TempDir;
+
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Written by hand, as `tar::Builder` refuses unsafe paths.
+    fn write_tar_gz(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            File::create(path).unwrap(),
+            Compression::default(),
+        ));
+        for (name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append(&header, *content).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    /// Archive of `entries` in the format of `name`, with an empty `destination` folder next to
+    /// it.
+    fn archive_with(name: &str, entries: &[(&str, &[u8])]) -> (TempDir, PathBuf, PathBuf) {
+        let folder = TempDir::new().unwrap();
+        let archive = folder.path().join(name);
+        if name.ends_with(".zip") {
+            write_zip(&archive, entries);
+        } else {
+            write_tar_gz(&archive, entries);
+        }
+        let destination = folder.path().join("destination");
+        std::fs::create_dir(&destination).unwrap();
+        (folder, archive, destination)
+    }
+
+    fn extracts_nested_files(name: &str) {
+        let (_folder, archive, destination) =
+            archive_with(name, &[("bin/tool", b"tool"), ("./README", b"readme")]);
+        extract_archive(&archive, &destination).unwrap();
+        assert_eq!(
+            std::fs::read(destination.join("bin/tool")).unwrap(),
+            b"tool"
+        );
+        assert_eq!(
+            std::fs::read(destination.join("README")).unwrap(),
+            b"readme"
+        );
+    }
+
+    fn refuses_parent_dir_entry(name: &str) {
+        let (folder, archive, destination) = archive_with(name, &[("../evil", b"evil")]);
+        assert!(extract_archive(&archive, &destination).is_err());
+        assert!(!folder.path().join("evil").exists());
+    }
+
+    fn refuses_absolute_entry(name: &str) {
+        let outside = TempDir::new().unwrap();
+        let target = outside.path().join("evil");
+        let (_folder, archive, destination) =
+            archive_with(name, &[(target.to_str().unwrap(), b"evil")]);
+        assert!(extract_archive(&archive, &destination).is_err());
+        assert!(!target.exists());
+        assert!(!destination.join(target.strip_prefix("/").unwrap()).exists());
+    }
+
+    #[cfg(unix)]
+    fn refuses_entry_below_symlink(name: &str) {
+        let outside = TempDir::new().unwrap();
+        let (_folder, archive, destination) = archive_with(name, &[("link/evil", b"evil")]);
+        std::os::unix::fs::symlink(outside.path(), destination.join("link")).unwrap();
+        assert!(extract_archive(&archive, &destination).is_err());
+        assert!(!outside.path().join("evil").exists());
+    }
+
+    #[cfg(unix)]
+    fn refuses_overwriting_symlink(name: &str) {
+        let outside = TempDir::new().unwrap();
+        let target = outside.path().join("target");
+        std::fs::write(&target, b"original").unwrap();
+        let (_folder, archive, destination) = archive_with(name, &[("file", b"evil")]);
+        std::os::unix::fs::symlink(&target, destination.join("file")).unwrap();
+        assert!(extract_archive(&archive, &destination).is_err());
+        assert_eq!(std::fs::read(&target).unwrap(), b"original");
+    }
+
+    #[test]
+    fn zip_extracts_nested_files() {
+        extracts_nested_files("a.zip");
+    }
+
+    #[test]
+    fn zip_refuses_parent_dir_entry() {
+        refuses_parent_dir_entry("a.zip");
+    }
+
+    #[test]
+    fn zip_refuses_absolute_entry() {
+        refuses_absolute_entry("a.zip");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_refuses_entry_below_symlink() {
+        refuses_entry_below_symlink("a.zip");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_refuses_overwriting_symlink() {
+        refuses_overwriting_symlink("a.zip");
+    }
+
+    #[test]
+    fn tar_extracts_nested_files() {
+        extracts_nested_files("a.tar.gz");
+    }
+
+    #[test]
+    fn tar_refuses_parent_dir_entry() {
+        refuses_parent_dir_entry("a.tar.gz");
+    }
+
+    #[test]
+    fn tar_refuses_absolute_entry() {
+        refuses_absolute_entry("a.tar.gz");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tar_refuses_entry_below_symlink() {
+        refuses_entry_below_symlink("a.tar.gz");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tar_refuses_overwriting_symlink() {
+        refuses_overwriting_symlink("a.tar.gz");
+    }
+
+    #[test]
+    fn extracts_only_the_given_archives() {
+        let (folder, archive, _destination) = archive_with("a.zip", &[("file", b"content")]);
+        let nested = folder.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        std::fs::rename(&archive, nested.join("a.zip")).unwrap();
+        // An archive of an earlier download, which must be left alone
+        let earlier = folder.path().join("earlier.zip");
+        write_zip(&earlier, &[("old", b"old content")]);
+        let readme = folder.path().join("README");
+        std::fs::write(&readme, b"readme").unwrap();
+
+        let files = vec![nested.join("a.zip"), readme.clone()];
+        let archives = extract_archives(&files, true).unwrap();
+        assert_eq!(archives, vec![nested.join("a.zip")]);
+        assert_eq!(std::fs::read(nested.join("file")).unwrap(), b"content");
+        assert!(!nested.join("a.zip").exists());
+        assert!(earlier.exists());
+        assert!(!folder.path().join("old").exists());
+        assert!(readme.exists());
+    }
+}
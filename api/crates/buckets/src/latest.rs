@@ -0,0 +1,461 @@
+use crate::client::S3Client;
+use crate::path_template::{DEFAULT_PATH_TEMPLATE, WINDOWS_PATH_TEMPLATE};
+use crate::timestamp::parse_timestamp;
+
+use std::error::Error;
+
+use glob::Pattern;
+
+use time::OffsetDateTime;
+
+/// Operating system and architecture combinations published for every sdk commit.
+pub const PLATFORMS: [(&str, &str); 3] = [
+    ("ubuntu-20.04", "amd64"),
+    ("ubuntu-20.04", "arm64"),
+    ("windows", "amd64"),
+];
+
+#[derive(Clone)]
+pub struct LatestBuild {
+    pub branch: String,
+    pub timestamp: String,
+    pub commit_hash: String,
+    /// Path to the commit folder, ending with `/`
+    pub path: String,
+}
+
+pub(crate) fn last_segment(folder: &str) -> &str {
+    folder.trim_end_matches('/').rsplit('/').next().unwrap()
+}
+
+/// Folder containing the `<timestamp>/<commit hash>/` folders of a branch of the sdk.
+pub fn branch_commit_folder(branch_name: &str) -> String {
+    DEFAULT_PATH_TEMPLATE.commit_folder(branch_name, "sdk")
+}
+
+/// Folder of the sdk artifacts for a platform, relative to a commit folder.
+pub fn platform_folder(os: &str, architecture: &str) -> Result<String, Box<dyn Error>> {
+    match os {
+        "ubuntu-20.04" => Ok(DEFAULT_PATH_TEMPLATE.platform_folder(os, architecture, "Release")),
+        "windows" => {
+            if architecture != "amd64" {
+                return Err("Unsupported architecture for windows".into());
+            }
+            Ok(WINDOWS_PATH_TEMPLATE.platform_folder(os, architecture, "Installers"))
+        }
+        &_ => Err("Unsupported operating system".into()),
+    }
+}
+
+pub(crate) async fn branch_names(client: &S3Client) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut branches = vec!["master".to_string()];
+    for group in ["success/release/", "success/other/"] {
+        for folder in client.list_folders(group).await? {
+            branches.push(last_segment(&folder).to_string());
+        }
+    }
+    Ok(branches)
+}
+
+/// All branches with successful builds.
+pub fn list_branches(client: &S3Client) -> Result<Vec<String>, Box<dyn Error>> {
+    client.block_on(branch_names(client))
+}
+
+/// Find the newest successful commit folder of a branch of the sdk, if it has any.
+pub fn find_latest_build(
+    client: &S3Client,
+    branch_name: &str,
+) -> Result<Option<LatestBuild>, Box<dyn Error>> {
+    find_latest_build_in(client, branch_name, &branch_commit_folder(branch_name))
+}
+
+/// Time of the newest upload below a folder. S3 gives every date in the same ISO 8601 format, so
+/// they can be compared as strings.
+fn last_modified(client: &S3Client, folder: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let newest = client
+        .list_all_objects_sync(folder)?
+        .into_iter()
+        .flat_map(|result| result.contents)
+        .map(|object| object.last_modified)
+        .max();
+    Ok(newest)
+}
+
+/// A timestamp folder of a branch, with the upload time of its newest file if another folder has
+/// the same date.
+struct TimestampFolder {
+    date: OffsetDateTime,
+    uploaded: Option<String>,
+    folder: String,
+}
+
+/// Timestamp folders of a branch, newest first. Folders with the same date are ordered by the
+/// upload time of their files, and folders whose name isn't a timestamp are left out.
+fn ordered_timestamp_folders(
+    client: &S3Client,
+    commit_folder: &str,
+) -> Result<Vec<TimestampFolder>, Box<dyn Error>> {
+    let mut dated = Vec::new();
+    for folder in client.list_folders_sync(commit_folder)? {
+        match parse_timestamp(last_segment(&folder)) {
+            Some(date) => dated.push((date, folder)),
+            None => tracing::warn!("Ignoring {}, its name is not a timestamp", folder),
+        }
+    }
+    dated.sort_by(|a, b| b.cmp(a));
+
+    // Only look up upload times for dates shared by several folders, as that lists every file
+    let mut ordered = Vec::new();
+    for group in dated.chunk_by(|a, b| a.0 == b.0) {
+        if group.len() == 1 {
+            let (date, folder) = group[0].clone();
+            ordered.push(TimestampFolder {
+                date,
+                uploaded: None,
+                folder,
+            });
+            continue;
+        }
+        let mut uploaded = Vec::new();
+        for (date, folder) in group {
+            uploaded.push((last_modified(client, folder)?, *date, folder.clone()));
+        }
+        uploaded.sort_by(|a, b| b.cmp(a));
+        ordered.extend(
+            uploaded
+                .into_iter()
+                .map(|(uploaded, date, folder)| TimestampFolder {
+                    date,
+                    uploaded,
+                    folder,
+                }),
+        );
+    }
+    Ok(ordered)
+}
+
+/// Timestamp folders of a branch with their dates, newest first. Folders with the same date are
+/// ordered by the upload time of their files, and folders whose name isn't a timestamp are left
+/// out.
+pub fn dated_timestamp_folders(
+    client: &S3Client,
+    commit_folder: &str,
+) -> Result<Vec<(OffsetDateTime, String)>, Box<dyn Error>> {
+    Ok(ordered_timestamp_folders(client, commit_folder)?
+        .into_iter()
+        .map(|timestamp| (timestamp.date, timestamp.folder))
+        .collect())
+}
+
+/// Fail if the timestamp folder at `index` can't be told apart from the one after it, as there's
+/// no telling which one is newer.
+fn check_unambiguous(timestamps: &[TimestampFolder], index: usize) -> Result<(), Box<dyn Error>> {
+    let timestamp = &timestamps[index];
+    match timestamps.get(index + 1) {
+        Some(next) if next.date == timestamp.date && next.uploaded == timestamp.uploaded => {
+            Err(format!(
+                "Ambiguous latest build: {} and {} have the same timestamp and upload time",
+                timestamp.folder, next.folder
+            )
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The build in a timestamp folder. Several commits in one timestamp folder are an error, as
+/// there's no telling which one is the latest.
+fn build_in_timestamp(
+    client: &S3Client,
+    branch_name: &str,
+    timestamp_folder: &str,
+) -> Result<Option<LatestBuild>, Box<dyn Error>> {
+    let commits = client.list_folders_sync(timestamp_folder)?;
+    let commit = match commits.as_slice() {
+        [] => return Ok(None),
+        [commit] => commit,
+        _ => {
+            let hashes: Vec<&str> = commits.iter().map(|commit| last_segment(commit)).collect();
+            return Err(format!(
+                "Ambiguous build of branch {}: {} contains the commits {}",
+                branch_name,
+                timestamp_folder,
+                hashes.join(", ")
+            )
+            .into());
+        }
+    };
+    Ok(Some(LatestBuild {
+        branch: branch_name.to_string(),
+        timestamp: last_segment(timestamp_folder).to_string(),
+        commit_hash: last_segment(commit).to_string(),
+        path: commit.to_string(),
+    }))
+}
+
+/// Find the newest commit folder in `commit_folder`, the folder of the commits of a branch.
+///
+/// Timestamp folders are compared as dates, and by the upload time of their files if the dates
+/// are equal.
+pub fn find_latest_build_in(
+    client: &S3Client,
+    branch_name: &str,
+    commit_folder: &str,
+) -> Result<Option<LatestBuild>, Box<dyn Error>> {
+    let timestamps = ordered_timestamp_folders(client, commit_folder)?;
+    match timestamps.first() {
+        Some(timestamp) => {
+            check_unambiguous(&timestamps, 0)?;
+            build_in_timestamp(client, branch_name, &timestamp.folder)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Which build of a branch to pick.
+pub struct BuildSelection {
+    /// Pick the n-th newest complete build, starting at 1
+    pub nth: usize,
+    /// Only consider builds with an earlier timestamp
+    pub before: Option<OffsetDateTime>,
+    /// Number of incomplete builds to skip before giving up
+    pub fallback: usize,
+}
+
+impl Default for BuildSelection {
+    fn default() -> Self {
+        BuildSelection {
+            nth: 1,
+            before: None,
+            fallback: 0,
+        }
+    }
+}
+
+/// Pick the build chosen by `selection` from timestamp folders ordered newest first, reading the
+/// build of a folder with `build_in` and keeping the ones accepted by `is_complete`.
+fn select_build<B, R, F>(
+    timestamps: &[TimestampFolder],
+    selection: &BuildSelection,
+    mut build_in: R,
+    mut is_complete: F,
+) -> Result<Option<B>, Box<dyn Error>>
+where
+    R: FnMut(&TimestampFolder) -> Result<Option<B>, Box<dyn Error>>,
+    F: FnMut(&B) -> Result<bool, Box<dyn Error>>,
+{
+    let mut complete = 0;
+    let mut incomplete = 0;
+    for (index, timestamp) in timestamps.iter().enumerate() {
+        if selection
+            .before
+            .is_some_and(|before| timestamp.date >= before)
+        {
+            continue;
+        }
+        check_unambiguous(timestamps, index)?;
+        let build = match build_in(timestamp)? {
+            Some(build) => build,
+            None => continue,
+        };
+        if !is_complete(&build)? {
+            incomplete += 1;
+            if incomplete > selection.fallback {
+                return Ok(None);
+            }
+            continue;
+        }
+        complete += 1;
+        if complete == selection.nth {
+            return Ok(Some(build));
+        }
+    }
+    Ok(None)
+}
+
+/// Find the build chosen by `selection` among the builds accepted by `is_complete`, walking back
+/// from the newest timestamp folder. Timestamp folders without a single commit are skipped, but
+/// ambiguous ones are an error rather than falling back to an older build.
+pub fn find_build_in<F>(
+    client: &S3Client,
+    branch_name: &str,
+    commit_folder: &str,
+    selection: &BuildSelection,
+    is_complete: F,
+) -> Result<Option<LatestBuild>, Box<dyn Error>>
+where
+    F: FnMut(&LatestBuild) -> Result<bool, Box<dyn Error>>,
+{
+    let timestamps = ordered_timestamp_folders(client, commit_folder)?;
+    let build_in =
+        |timestamp: &TimestampFolder| build_in_timestamp(client, branch_name, &timestamp.folder);
+    select_build(&timestamps, selection, build_in, is_complete)
+}
+
+/// Patterns of `required` (file names relative to `folder`, or globs like `*.deb`) that no file
+/// below `folder` matches. Every pattern is missing if the folder is empty.
+pub fn missing_files(
+    client: &S3Client,
+    folder: &str,
+    required: &[String],
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let relative_paths: Vec<String> = client
+        .list_all_objects_sync(folder)?
+        .into_iter()
+        .flat_map(|result| result.contents)
+        .filter_map(|object| object.key.strip_prefix(folder).map(str::to_string))
+        .collect();
+    let mut missing = Vec::new();
+    for pattern in required {
+        let glob = Pattern::new(pattern)
+            .map_err(|e| format!("Invalid file pattern {}: {}", pattern, e))?;
+        if !relative_paths.iter().any(|path| glob.matches(path)) {
+            missing.push(pattern.clone());
+        }
+    }
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use time::{Date, Month};
+
+    fn may(day: u8) -> OffsetDateTime {
+        let date = Date::from_calendar_date(2024, Month::May, day).unwrap();
+        date.midnight().assume_utc()
+    }
+
+    /// Timestamp folders of the given days of May, newest first like
+    /// `ordered_timestamp_folders`
+    fn timestamps(days: &[u8]) -> Vec<TimestampFolder> {
+        days.iter()
+            .map(|day| TimestampFolder {
+                date: may(*day),
+                uploaded: None,
+                folder: format!("success/master/sdk/commit/2024-05-{:02}/", day),
+            })
+            .collect()
+    }
+
+    /// Day of the build picked from builds on `days`, where the folders of `empty` days have no
+    /// commit and the builds of `incomplete` days are incomplete.
+    fn select(
+        days: &[u8],
+        selection: &BuildSelection,
+        empty: &[u8],
+        incomplete: &[u8],
+    ) -> Result<Option<u8>, Box<dyn Error>> {
+        let build_in = |timestamp: &TimestampFolder| {
+            let day = timestamp.date.day();
+            Ok((!empty.contains(&day)).then_some(day))
+        };
+        let is_complete = |day: &u8| Ok(!incomplete.contains(day));
+        select_build(&timestamps(days), selection, build_in, is_complete)
+    }
+
+    fn fallback(fallback: usize) -> BuildSelection {
+        BuildSelection {
+            fallback,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn newest_complete_build() {
+        let selection = BuildSelection::default();
+        assert_eq!(select(&[5, 4, 3], &selection, &[], &[]).unwrap(), Some(5));
+        assert_eq!(select(&[], &selection, &[], &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn incomplete_builds_are_skipped_up_to_the_fallback() {
+        let days = [5, 4, 3, 2];
+        assert_eq!(select(&days, &fallback(2), &[], &[5, 4]).unwrap(), Some(3));
+        assert_eq!(select(&days, &fallback(1), &[], &[5, 4]).unwrap(), None);
+        // Without fallback only the newest build is considered
+        assert_eq!(select(&days, &fallback(0), &[], &[5]).unwrap(), None);
+        assert_eq!(
+            select(&days, &fallback(10), &[], &[5, 4, 3, 2]).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn folders_without_commit_are_not_incomplete_builds() {
+        assert_eq!(
+            select(&[5, 4, 3], &fallback(0), &[5, 4], &[]).unwrap(),
+            Some(3)
+        );
+    }
+
+    fn nth(nth: usize) -> BuildSelection {
+        BuildSelection {
+            nth,
+            fallback: 10,
+            ..Default::default()
+        }
+    }
+
+    fn before(day: u8) -> BuildSelection {
+        BuildSelection {
+            before: Some(may(day)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn nth_complete_build() {
+        let days = [5, 4, 3, 2];
+        assert_eq!(select(&days, &nth(2), &[], &[]).unwrap(), Some(4));
+        // Incomplete builds aren't counted
+        assert_eq!(select(&days, &nth(2), &[], &[4]).unwrap(), Some(3));
+        assert_eq!(select(&days, &nth(4), &[], &[]).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn nth_past_the_end() {
+        assert_eq!(select(&[5, 4, 3], &nth(4), &[], &[]).unwrap(), None);
+        assert_eq!(select(&[5, 4, 3], &nth(3), &[], &[4]).unwrap(), None);
+    }
+
+    #[test]
+    fn before_leaves_out_builds_at_and_after_it() {
+        let days = [5, 4, 3, 2];
+        // A build at the same time isn't before it
+        assert_eq!(select(&days, &before(4), &[], &[]).unwrap(), Some(3));
+        assert_eq!(select(&days, &before(6), &[], &[]).unwrap(), Some(5));
+    }
+
+    #[test]
+    fn before_a_missing_timestamp() {
+        // No build on the 4th, the one before it is picked
+        assert_eq!(select(&[6, 5, 3], &before(4), &[], &[]).unwrap(), Some(3));
+        // No build before it at all
+        assert_eq!(select(&[6, 5, 3], &before(3), &[], &[]).unwrap(), None);
+        assert_eq!(select(&[6, 5, 3], &before(1), &[], &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn before_with_nth_and_fallback() {
+        let selection = BuildSelection {
+            nth: 2,
+            before: Some(may(6)),
+            fallback: 1,
+        };
+        // Builds from the 6th on are neither counted nor incomplete
+        let days = [7, 6, 5, 4, 3];
+        assert_eq!(select(&days, &selection, &[], &[7, 6, 5]).unwrap(), Some(3));
+        assert_eq!(select(&days, &selection, &[], &[5, 4]).unwrap(), None);
+    }
+
+    #[test]
+    fn ambiguous_folders_are_an_error_instead_of_a_fallback() {
+        let selection = fallback(10);
+        assert!(select(&[5, 5, 4], &selection, &[], &[]).is_err());
+        // Only when they are reached
+        assert_eq!(select(&[6, 5, 5], &selection, &[], &[]).unwrap(), Some(6));
+    }
+}
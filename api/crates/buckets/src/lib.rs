@@ -1,10 +1,57 @@
 mod artifact_node;
+mod bucket_list;
+mod client;
+mod commit_hash;
+mod commit_index;
+mod extract;
+mod latest;
+mod path_template;
+mod progress;
+mod retry;
 mod s3buckets;
+mod search;
+mod stats;
+mod timestamp;
 
 // Export functions from library and other modules within this library from here
+pub use crate::client::S3Client;
+pub use crate::commit_hash::matches_commit_hash;
+pub use crate::commit_hash::CommitHashError;
+pub use crate::commit_index::build_commit_index;
+pub use crate::commit_index::list_commit_folders;
+pub use crate::commit_index::lookup_artifact_with_commit_hash;
+pub use crate::commit_index::lookup_commit_artifacts_in_all_branches;
+pub use crate::commit_index::CommitIndex;
+pub use crate::commit_index::IndexLocation;
+pub use crate::extract::extract_archive;
+pub use crate::extract::extract_archives;
+pub use crate::latest::branch_commit_folder;
+pub use crate::latest::dated_timestamp_folders;
+pub use crate::latest::find_build_in;
+pub use crate::latest::find_latest_build;
+pub use crate::latest::find_latest_build_in;
+pub use crate::latest::list_branches;
+pub use crate::latest::missing_files;
+pub use crate::latest::platform_folder;
+pub use crate::latest::BuildSelection;
+pub use crate::latest::LatestBuild;
+pub use crate::latest::PLATFORMS;
+pub use crate::path_template::branch_group;
+pub use crate::path_template::PathTemplate;
+pub use crate::path_template::DEFAULT_TEMPLATE;
+pub use crate::path_template::WINDOWS_TEMPLATE;
+pub use crate::retry::RetryPolicy;
+pub use crate::s3buckets::download_artifacts;
 pub use crate::s3buckets::download_artifacts_sync;
 pub use crate::s3buckets::find_artifact_with_commit_hash;
-pub use crate::s3buckets::list_all_objects;
-pub use crate::s3buckets::list_folders_in_prefix;
+pub use crate::s3buckets::find_commit_artifacts_in_all_branches;
+pub use crate::s3buckets::presign_folder;
 pub use crate::s3buckets::print_flat_list;
 pub use crate::s3buckets::print_tree_list;
+pub use crate::s3buckets::CommitArtifact;
+pub use crate::s3buckets::ObjectReader;
+pub use crate::search::search_objects;
+pub use crate::stats::s3_call_stats;
+pub use crate::stats::S3CallStats;
+pub use crate::stats::LATENCY_BUCKETS;
+pub use crate::timestamp::parse_timestamp;
@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::sync::LazyLock;
+
+/// Layout of the sdk artifacts of linux builds.
+pub const DEFAULT_TEMPLATE: &str =
+    "success/{branch_group}/{branch}/{product}/commit/{timestamp}/{hash}/{os}/{arch}/{config}/";
+
+/// [`DEFAULT_TEMPLATE`], parsed once. It is valid, see the tests.
+pub(crate) static DEFAULT_PATH_TEMPLATE: LazyLock<PathTemplate> =
+    LazyLock::new(|| PathTemplate::new(DEFAULT_TEMPLATE).unwrap());
+
+/// Layout of the sdk artifacts of windows builds, which don't have the architecture in the path.
+pub const WINDOWS_TEMPLATE: &str =
+    "success/{branch_group}/{branch}/{product}/commit/{timestamp}/{hash}/{os}/{config}/";
+
+/// [`WINDOWS_TEMPLATE`], parsed once. It is valid, see the tests.
+pub(crate) static WINDOWS_PATH_TEMPLATE: LazyLock<PathTemplate> =
+    LazyLock::new(|| PathTemplate::new(WINDOWS_TEMPLATE).unwrap());
+
+/// Placeholders allowed before `{timestamp}`
+const COMMIT_FOLDER_PLACEHOLDERS: [&str; 3] = ["branch_group", "branch", "product"];
+
+/// Placeholders allowed after `{hash}/`
+const PLATFORM_FOLDER_PLACEHOLDERS: [&str; 3] = ["os", "arch", "config"];
+
+/// Group folder of a branch: none for master, `release` for release branches and `other` for
+/// everything else.
+pub fn branch_group(branch_name: &str) -> &'static str {
+    if branch_name == "master" {
+        ""
+    } else if branch_name.starts_with("release-") {
+        "release"
+    } else {
+        "other"
+    }
+}
+
+/// Path of an artifact folder in the bucket with `{placeholder}`s for the parts that vary, e.g.
+/// [`DEFAULT_TEMPLATE`].
+///
+/// The part before `{timestamp}` is the folder containing every commit of a branch, and the part
+/// after `{hash}/` is the folder of a platform relative to a commit folder. Segments that are
+/// empty after substitution are dropped, so `success/{branch_group}/{branch}/` of master is
+/// `success/master/`.
+pub struct PathTemplate {
+    /// Template up to `{timestamp}`
+    commit_folder: Vec<Part>,
+    /// Template after `{hash}/`
+    platform_folder: Vec<Part>,
+}
+
+/// Piece of a parsed template
+enum Part {
+    Text(String),
+    /// Name of a placeholder
+    Placeholder(String),
+}
+
+/// Split a part of `template` into text and placeholders, which must be `allowed`.
+fn parse(template: &str, part: &str, allowed: &[&str]) -> Result<Vec<Part>, Box<dyn Error>> {
+    let mut parts = Vec::new();
+    let mut rest = part;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in template {}", template))?;
+        let name = &rest[start + 1..start + end];
+        if !allowed.contains(&name) {
+            return Err(format!(
+                "Unexpected placeholder {{{}}} in {} of template {}, expected one of {}",
+                name,
+                part,
+                template,
+                allowed.join(", ")
+            )
+            .into());
+        }
+        parts.push(Part::Text(rest[..start].to_string()));
+        parts.push(Part::Placeholder(name.to_string()));
+        rest = &rest[start + end + 1..];
+    }
+    parts.push(Part::Text(rest.to_string()));
+    Ok(parts)
+}
+
+/// Substitute the placeholders left to right, so that values are never substituted themselves.
+fn render(parts: &[Part], values: &[(&str, &str)]) -> String {
+    let path: String = parts
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) => text.as_str(),
+            Part::Placeholder(name) => values
+                .iter()
+                .find(|(value_name, _)| value_name == name)
+                .map_or("", |(_, value)| value),
+        })
+        .collect();
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| format!("{}/", segment))
+        .collect()
+}
+
+impl PathTemplate {
+    pub fn new(template: &str) -> Result<PathTemplate, Box<dyn Error>> {
+        let (commit_folder, platform_folder) = template
+            .split_once("{timestamp}/{hash}/")
+            .ok_or_else(|| {
+                format!(
+                    "Template {} must contain {{timestamp}}/{{hash}}/ followed by the platform folder",
+                    template
+                )
+            })?;
+        Ok(PathTemplate {
+            commit_folder: parse(template, commit_folder, &COMMIT_FOLDER_PLACEHOLDERS)?,
+            platform_folder: parse(template, platform_folder, &PLATFORM_FOLDER_PLACEHOLDERS)?,
+        })
+    }
+
+    /// Folder containing the `<timestamp>/<commit hash>/` folders of a branch.
+    pub fn commit_folder(&self, branch_name: &str, product: &str) -> String {
+        let values = [
+            ("branch_group", branch_group(branch_name)),
+            ("branch", branch_name),
+            ("product", product),
+        ];
+        render(&self.commit_folder, &values)
+    }
+
+    /// Folder of the artifacts for a platform, relative to a commit folder.
+    pub fn platform_folder(&self, os: &str, architecture: &str, config: &str) -> String {
+        let values = [("os", os), ("arch", architecture), ("config", config)];
+        render(&self.platform_folder, &values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_template() {
+        assert_eq!(
+            DEFAULT_PATH_TEMPLATE.commit_folder("master", "sdk"),
+            "success/master/sdk/commit/"
+        );
+        assert_eq!(
+            DEFAULT_PATH_TEMPLATE.commit_folder("release-2.13", "sdk"),
+            "success/release/release-2.13/sdk/commit/"
+        );
+        assert_eq!(
+            DEFAULT_PATH_TEMPLATE.commit_folder("feature-x", "sdk"),
+            "success/other/feature-x/sdk/commit/"
+        );
+        assert_eq!(
+            DEFAULT_PATH_TEMPLATE.platform_folder("ubuntu-20.04", "amd64", "Release"),
+            "ubuntu-20.04/amd64/Release/"
+        );
+    }
+
+    #[test]
+    fn windows_template() {
+        assert_eq!(
+            WINDOWS_PATH_TEMPLATE.platform_folder("windows", "amd64", "Release"),
+            "windows/Release/"
+        );
+    }
+
+    #[test]
+    fn values_with_placeholders_are_left_as_they_are() {
+        assert_eq!(
+            DEFAULT_PATH_TEMPLATE.commit_folder("feature-{product}", "{branch}"),
+            "success/other/feature-{product}/{branch}/commit/"
+        );
+        assert_eq!(
+            DEFAULT_PATH_TEMPLATE.platform_folder("{arch}", "{config}", "{os}"),
+            "{arch}/{config}/{os}/"
+        );
+    }
+
+    #[test]
+    fn custom_template() {
+        let template =
+            PathTemplate::new("builds/{product}/{branch}/{timestamp}/{hash}/{config}-{os}/")
+                .unwrap();
+        assert_eq!(
+            template.commit_folder("master", "sdk"),
+            "builds/sdk/master/"
+        );
+        assert_eq!(
+            template.platform_folder("windows", "amd64", "Debug"),
+            "Debug-windows/"
+        );
+    }
+
+    #[test]
+    fn empty_config_is_dropped() {
+        assert_eq!(
+            DEFAULT_PATH_TEMPLATE.platform_folder("ubuntu-20.04", "arm64", ""),
+            "ubuntu-20.04/arm64/"
+        );
+    }
+
+    #[test]
+    fn template_without_timestamp_and_hash_is_refused() {
+        assert!(PathTemplate::new("success/{branch}/{hash}/{os}/").is_err());
+        assert!(PathTemplate::new("success/{branch}/{timestamp}/{os}/").is_err());
+        assert!(PathTemplate::new("success/{branch}/{hash}/{timestamp}/{os}/").is_err());
+    }
+
+    #[test]
+    fn misplaced_or_unclosed_placeholders_are_refused() {
+        assert!(PathTemplate::new("success/{os}/{timestamp}/{hash}/").is_err());
+        assert!(PathTemplate::new("success/{branch}/{timestamp}/{hash}/{branch}/").is_err());
+        assert!(PathTemplate::new("success/{branch/{timestamp}/{hash}/").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use indicatif::{
+    HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle,
+};
+
+use std::io::IsTerminal;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use tokio::io::AsyncWrite;
+
+/// How often the progress is printed when not on a terminal
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Progress of downloading several files, drawn as bars on a terminal and printed as a line
+/// every few seconds otherwise, e.g. in CI logs. Everything goes to stderr, leaving stdout to
+/// what scripts ask for.
+pub(crate) struct DownloadProgress {
+    bars: MultiProgress,
+    /// Bytes of every file, also counted when the bars are hidden
+    total: ProgressBar,
+    total_files: u64,
+    done_files: AtomicU64,
+    terminal: bool,
+    start: Instant,
+    last_report: Mutex<Instant>,
+}
+
+fn rate(bytes: u64, elapsed: Duration) -> HumanBytes {
+    HumanBytes((bytes as f64 / elapsed.as_secs_f64().max(0.001)) as u64)
+}
+
+/// Line printed every few seconds when not on a terminal.
+fn progress_line(
+    done_files: u64,
+    total_files: u64,
+    bytes: u64,
+    total_bytes: u64,
+    elapsed: Duration,
+) -> String {
+    let eta = if bytes > 0 {
+        let remaining = total_bytes.saturating_sub(bytes) as f64 / bytes as f64;
+        HumanDuration(elapsed.mul_f64(remaining)).to_string()
+    } else {
+        "unknown".to_string()
+    };
+    format!(
+        "Downloaded {}/{} files, {}/{} ({}/s, ETA {})",
+        done_files,
+        total_files,
+        HumanBytes(bytes),
+        HumanBytes(total_bytes),
+        rate(bytes, elapsed),
+        eta
+    )
+}
+
+/// Line printed once every file is downloaded.
+fn summary_line(files: u64, bytes: u64, elapsed: Duration) -> String {
+    format!(
+        "Downloaded {} files, {} in {:.1}s ({}/s)",
+        files,
+        HumanBytes(bytes),
+        elapsed.as_secs_f64(),
+        rate(bytes, elapsed)
+    )
+}
+
+impl DownloadProgress {
+    pub(crate) fn new(total_files: u64, total_bytes: u64) -> DownloadProgress {
+        let terminal = std::io::stderr().is_terminal();
+        let bars = MultiProgress::with_draw_target(if terminal {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        });
+        let total = bars.add(ProgressBar::new(total_bytes));
+        total.set_style(
+            ProgressStyle::with_template(
+                "[{elapsed_precise}] [{wide_bar}] {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta}) {msg}",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        total.set_message(format!("0/{} files", total_files));
+        DownloadProgress {
+            bars,
+            total,
+            total_files,
+            done_files: AtomicU64::new(0),
+            terminal,
+            start: Instant::now(),
+            last_report: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub(crate) fn start_file(&self, key: &str, size: u64) -> FileProgress<'_> {
+        let bar = if self.terminal {
+            let bar = self.bars.insert_before(&self.total, ProgressBar::new(size));
+            bar.set_style(
+                ProgressStyle::with_template("{wide_msg} {bytes}/{total_bytes} ({bytes_per_sec})")
+                    .unwrap(),
+            );
+            bar.set_message(key.to_string());
+            bar
+        } else {
+            eprintln!("Downloading file: {:?}", key);
+            ProgressBar::hidden()
+        };
+        FileProgress {
+            download: self,
+            bar,
+        }
+    }
+
+    /// Print the overall progress if the last line is old enough.
+    fn report(&self) {
+        if self.terminal {
+            return;
+        }
+        let mut last_report = self.last_report.lock().unwrap();
+        if last_report.elapsed() < REPORT_INTERVAL {
+            return;
+        }
+        *last_report = Instant::now();
+        let bytes = self.total.position();
+        eprintln!(
+            "{}",
+            progress_line(
+                self.done_files.load(Ordering::Relaxed),
+                self.total_files,
+                bytes,
+                self.total.length().unwrap_or(bytes),
+                self.start.elapsed(),
+            )
+        );
+    }
+
+    /// Remove the bars and print how much was downloaded in how long.
+    pub(crate) fn finish(&self) {
+        self.total.finish_and_clear();
+        eprintln!(
+            "{}",
+            summary_line(
+                self.done_files.load(Ordering::Relaxed),
+                self.total.position(),
+                self.start.elapsed(),
+            )
+        );
+    }
+}
+
+/// Progress of a single file of a [`DownloadProgress`].
+pub(crate) struct FileProgress<'a> {
+    download: &'a DownloadProgress,
+    bar: ProgressBar,
+}
+
+impl FileProgress<'_> {
+    fn advance(&self, bytes: u64) {
+        self.bar.inc(bytes);
+        self.download.total.inc(bytes);
+        self.download.report();
+    }
+
+    /// Forget the bytes of a failed attempt, the file is downloaded again from the start.
+    pub(crate) fn restart(&self) {
+        self.download.total.dec(self.bar.position());
+        self.bar.set_position(0);
+    }
+
+    pub(crate) fn finish(self) {
+        self.bar.finish_and_clear();
+        let done_files = self.download.done_files.fetch_add(1, Ordering::Relaxed) + 1;
+        self.download.total.set_message(format!(
+            "{}/{} files",
+            done_files, self.download.total_files
+        ));
+        self.download.report();
+    }
+
+    /// Writer counting the bytes written to `inner` as downloaded.
+    pub(crate) fn writer<W: AsyncWrite + Unpin>(&self, inner: W) -> ProgressWriter<'_, W> {
+        ProgressWriter {
+            inner,
+            progress: self,
+        }
+    }
+}
+
+pub(crate) struct ProgressWriter<'a, W> {
+    inner: W,
+    progress: &'a FileProgress<'a>,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for ProgressWriter<'_, W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            self.progress.advance(written as u64);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn progress_line_with_eta() {
+        assert_eq!(
+            progress_line(1, 4, 25 * MIB, 100 * MIB, Duration::from_secs(10)),
+            "Downloaded 1/4 files, 25.00 MiB/100.00 MiB (2.50 MiB/s, ETA 30 seconds)"
+        );
+    }
+
+    #[test]
+    fn progress_line_before_the_first_byte() {
+        assert_eq!(
+            progress_line(0, 2, 0, 3 * MIB, Duration::from_secs(5)),
+            "Downloaded 0/2 files, 0 B/3.00 MiB (0 B/s, ETA unknown)"
+        );
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(
+            summary_line(2, 128, Duration::from_millis(77)),
+            "Downloaded 2 files, 128 B in 0.1s (1.62 KiB/s)"
+        );
+        // A download taking no time doesn't divide by zero
+        assert_eq!(
+            summary_line(0, 0, Duration::ZERO),
+            "Downloaded 0 files, 0 B in 0.0s (0 B/s)"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use s3::error::S3Error;
+
+use std::error::Error;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// How failed S3 calls are retried. The wait before the n-th retry is `initial_backoff`
+/// doubled n - 1 times, at most `max_backoff`, shortened by a random part of up to `jitter` of
+/// it so that parallel downloads don't retry in lockstep.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Calls made in total before giving up, 1 to never retry
+    pub attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Between 0 and 1
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 4,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            jitter: 0.5,
+        }
+    }
+}
+
+fn var_or<T, F>(var: &F, name: &str, default: T) -> Result<T, Box<dyn Error>>
+where
+    T: std::str::FromStr,
+    F: Fn(&str) -> Option<String>,
+{
+    match var(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("Invalid value for {}: {}", name, value).into()),
+        None => Ok(default),
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy with the values set in `S3_RETRY_ATTEMPTS`, `S3_RETRY_BACKOFF_MS`,
+    /// `S3_RETRY_MAX_BACKOFF_MS` and `S3_RETRY_JITTER`.
+    pub fn from_env() -> Result<RetryPolicy, Box<dyn Error>> {
+        RetryPolicy::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// The default policy with the values of the `S3_RETRY_*` variables given by `var`.
+    fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> Result<RetryPolicy, Box<dyn Error>> {
+        let default = RetryPolicy::default();
+        let policy = RetryPolicy {
+            attempts: var_or(&var, "S3_RETRY_ATTEMPTS", default.attempts)?,
+            initial_backoff: Duration::from_millis(var_or(
+                &var,
+                "S3_RETRY_BACKOFF_MS",
+                default.initial_backoff.as_millis() as u64,
+            )?),
+            max_backoff: Duration::from_millis(var_or(
+                &var,
+                "S3_RETRY_MAX_BACKOFF_MS",
+                default.max_backoff.as_millis() as u64,
+            )?),
+            jitter: var_or(&var, "S3_RETRY_JITTER", default.jitter)?,
+        };
+        if policy.attempts == 0 {
+            return Err("S3_RETRY_ATTEMPTS must be at least 1".into());
+        }
+        if !(0.0..=1.0).contains(&policy.jitter) {
+            return Err("S3_RETRY_JITTER must be between 0 and 1".into());
+        }
+        Ok(policy)
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_backoff);
+        backoff.mul_f64(1.0 - self.jitter * fastrand::f64())
+    }
+
+    /// Make `call` until it succeeds, fails with an error retrying won't help with, or every
+    /// attempt is used up.
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        operation: &str,
+        key: &str,
+        mut call: F,
+    ) -> Result<T, S3Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, S3Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(e) if attempt < self.attempts && is_retryable(&e) => {
+                    let backoff = self.backoff(attempt);
+                    tracing::warn!(
+                        "{} of {} failed, retrying in {} ms ({}/{}): {}",
+                        operation,
+                        key,
+                        backoff.as_millis(),
+                        attempt,
+                        self.attempts - 1,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Whether an error may go away by itself, like a dropped connection or an overloaded server,
+/// rather than being wrong credentials or a missing key.
+fn is_retryable(error: &S3Error) -> bool {
+    match error {
+        S3Error::Http(status, _) => matches!(status, 408 | 429 | 500 | 502 | 503 | 504),
+        S3Error::Reqwest(e) => !e.is_builder() && !e.is_redirect() && !e.is_status(),
+        // Reading the response, not local errors like a missing folder or a full disk
+        S3Error::Io(e) => matches!(
+            e.kind(),
+            ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::TimedOut
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::Interrupted
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            attempts: 4,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter,
+        }
+    }
+
+    fn from_vars(vars: &[(&str, &str)]) -> Result<RetryPolicy, Box<dyn Error>> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        RetryPolicy::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn server_errors_and_throttling_are_retried() {
+        for status in [408, 429, 500, 502, 503, 504] {
+            assert!(
+                is_retryable(&S3Error::Http(status, String::new())),
+                "{}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        for status in [400, 401, 403, 404, 416, 501] {
+            assert!(
+                !is_retryable(&S3Error::Http(status, String::new())),
+                "{}",
+                status
+            );
+        }
+        assert!(!is_retryable(&S3Error::MaxExpiry(0)));
+    }
+
+    #[test]
+    fn only_network_io_errors_are_retried() {
+        let io = |kind| S3Error::Io(std::io::Error::from(kind));
+        assert!(is_retryable(&io(ErrorKind::ConnectionReset)));
+        assert!(is_retryable(&io(ErrorKind::TimedOut)));
+        assert!(is_retryable(&io(ErrorKind::UnexpectedEof)));
+        assert!(!is_retryable(&io(ErrorKind::NotFound)));
+        assert!(!is_retryable(&io(ErrorKind::PermissionDenied)));
+        assert!(!is_retryable(&io(ErrorKind::StorageFull)));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = policy(0.0);
+        let backoffs: Vec<u128> = (1..=5)
+            .map(|retry| policy.backoff(retry).as_millis())
+            .collect();
+        assert_eq!(backoffs, vec![100, 200, 400, 500, 500]);
+        assert_eq!(policy.backoff(100), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitter_only_shortens_the_backoff() {
+        let policy = policy(0.5);
+        for _ in 0..100 {
+            let backoff = policy.backoff(2);
+            assert!(backoff >= Duration::from_millis(100) && backoff <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn policy_defaults_without_variables() {
+        let policy = from_vars(&[]).unwrap();
+        let default = RetryPolicy::default();
+        assert_eq!(policy.attempts, default.attempts);
+        assert_eq!(policy.initial_backoff, default.initial_backoff);
+        assert_eq!(policy.max_backoff, default.max_backoff);
+        assert_eq!(policy.jitter, default.jitter);
+    }
+
+    #[test]
+    fn policy_from_variables() {
+        let policy = from_vars(&[
+            ("S3_RETRY_ATTEMPTS", "2"),
+            ("S3_RETRY_BACKOFF_MS", "50"),
+            ("S3_RETRY_MAX_BACKOFF_MS", "1000"),
+            ("S3_RETRY_JITTER", "0"),
+        ])
+        .unwrap();
+        assert_eq!(policy.attempts, 2);
+        assert_eq!(policy.initial_backoff, Duration::from_millis(50));
+        assert_eq!(policy.max_backoff, Duration::from_millis(1000));
+        assert_eq!(policy.jitter, 0.0);
+    }
+
+    #[test]
+    fn invalid_variables_are_refused() {
+        assert!(from_vars(&[("S3_RETRY_ATTEMPTS", "0")]).is_err());
+        assert!(from_vars(&[("S3_RETRY_ATTEMPTS", "many")]).is_err());
+        assert!(from_vars(&[("S3_RETRY_BACKOFF_MS", "-1")]).is_err());
+        assert!(from_vars(&[("S3_RETRY_JITTER", "1.5")]).is_err());
+    }
+
+    async fn calls_until_done(error: fn() -> S3Error, failures: u32) -> (u32, bool) {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..policy(0.0)
+        };
+        let calls = AtomicU32::new(0);
+        let result = policy
+            .run("get", "key", || async {
+                if calls.fetch_add(1, Ordering::Relaxed) < failures {
+                    Err(error())
+                } else {
+                    Ok(())
+                }
+            })
+            .await;
+        (calls.load(Ordering::Relaxed), result.is_ok())
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let unavailable = || S3Error::Http(503, String::new());
+        assert_eq!(calls_until_done(unavailable, 2).await, (3, true));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_every_attempt() {
+        let unavailable = || S3Error::Http(503, String::new());
+        assert_eq!(calls_until_done(unavailable, 10).await, (4, false));
+    }
+
+    #[tokio::test]
+    async fn fails_right_away_on_fatal_errors() {
+        let forbidden = || S3Error::Http(403, String::new());
+        assert_eq!(calls_until_done(forbidden, 10).await, (1, false));
+    }
+}
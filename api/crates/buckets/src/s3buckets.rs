@@ -1,103 +1,120 @@
 use crate::artifact_node::{build_artifact_tree, print_artifact_tree, ArtifactNode};
+use crate::client::S3Client;
+use crate::commit_hash::{
+    check_unique_commit, is_full_commit_hash, matches_commit_hash, validate_commit_hash,
+};
+use crate::latest::{branch_commit_folder, branch_names, last_segment};
+use crate::progress::{DownloadProgress, FileProgress};
+use crate::stats::timed;
+use crate::timestamp::parse_timestamp;
 
-use cli::{read_credentials, S3Config};
-
-use s3::bucket::Bucket;
-use s3::creds::Credentials;
-use s3::region::Region;
 use s3::serde_types::ListBucketResult;
-use s3::serde_types::Object;
 
 use fs_more::directory::DestinationDirectoryRule;
 use fs_more::directory::DirectoryMoveOptions;
 
+use futures::stream::{self, StreamExt};
+
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Reverse;
 use std::error::Error;
-use std::fs::File;
-use std::io::prelude::*;
+use std::future::Future;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use tokio::runtime::Runtime;
-
-fn get_bucket() -> Result<Bucket, Box<dyn Error>> {
-    let S3Config {
-        hostname,
-        access_key,
-        secret_key,
-        bucket_name,
-    } = read_credentials();
-    let region = Region::Custom {
-        region: "".to_owned(),
-        endpoint: format!("http://{}", hostname),
-    };
-    let credentials = Credentials {
-        access_key: Some(access_key),
-        secret_key: Some(secret_key),
-        security_token: None,
-        session_token: None,
-        expiration: None,
-    };
-    let bucket =
-        Bucket::new(bucket_name.as_str(), region.clone(), credentials.clone())?.with_path_style();
-    Ok(bucket)
-}
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, DuplexStream, Take};
+use tokio::runtime::Handle;
 
-async fn list_objects(prefix: &str) -> Result<Vec<ListBucketResult>, Box<dyn Error>> {
-    let bucket = get_bucket()?;
-    let objects = bucket
-        .list(String::from(prefix), Some("/".to_owned()))
+impl S3Client {
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ListBucketResult>, Box<dyn Error>> {
+        let objects = self
+            .retried("list", prefix, || {
+                self.bucket()
+                    .list(String::from(prefix), Some("/".to_owned()))
+            })
+            .await?;
+        Ok(objects)
+    }
+
+    /// Check that the bucket is reachable with the configured credentials by listing a single
+    /// key.
+    pub async fn check_bucket(&self) -> Result<(), Box<dyn Error>> {
+        timed(
+            "list",
+            self.bucket()
+                .list_page(String::new(), Some("/".to_owned()), None, None, Some(1)),
+        )
         .await?;
-    return Ok(objects);
-}
+        Ok(())
+    }
 
-fn find_and_append_objects(
-    prefix: &str,
-    mut output_objects: &mut Vec<ListBucketResult>,
-) -> Result<(), Box<dyn Error>> {
-    let objects_to_visit = Runtime::new().unwrap().block_on(list_objects(prefix))?;
-    for object in &objects_to_visit {
-        match &object.common_prefixes {
-            None => continue,
-            Some(common_prefixes) => {
-                for common_prefix in common_prefixes {
-                    find_and_append_objects(common_prefix.prefix.as_str(), &mut output_objects)?;
+    pub fn check_bucket_sync(&self) -> Result<(), Box<dyn Error>> {
+        self.block_on(self.check_bucket())
+    }
+
+    async fn find_and_append_objects(
+        &self,
+        prefix: &str,
+        output_objects: &mut Vec<ListBucketResult>,
+    ) -> Result<(), Box<dyn Error>> {
+        let objects_to_visit = self.list_objects(prefix).await?;
+        for object in &objects_to_visit {
+            match &object.common_prefixes {
+                None => continue,
+                Some(common_prefixes) => {
+                    for common_prefix in common_prefixes {
+                        Box::pin(self.find_and_append_objects(
+                            common_prefix.prefix.as_str(),
+                            output_objects,
+                        ))
+                        .await?;
+                    }
                 }
             }
         }
+        for object in objects_to_visit {
+            output_objects.push(object);
+        }
+        Ok(())
     }
-    for object in objects_to_visit {
-        output_objects.push(object);
-    }
-    Ok(())
-}
 
-pub fn list_all_objects(prefix: &str) -> Result<Vec<ListBucketResult>, Box<dyn Error>> {
-    let mut objects: Vec<ListBucketResult> = Vec::new();
-    find_and_append_objects(prefix, &mut objects)?;
-    Ok(objects)
-}
+    /// Every file below a prefix, as the listings of each folder.
+    pub async fn list_all_objects(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<ListBucketResult>, Box<dyn Error>> {
+        let mut objects: Vec<ListBucketResult> = Vec::new();
+        self.find_and_append_objects(prefix, &mut objects).await?;
+        Ok(objects)
+    }
 
+    pub fn list_all_objects_sync(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<ListBucketResult>, Box<dyn Error>> {
+        self.block_on(self.list_all_objects(prefix))
+    }
 
-pub fn list_folders_in_prefix(
-    prefix: &str
-) -> Result<Vec<String>, Box<dyn Error>> {
-    let mut output: Vec<String> = Vec::new();
-    let objects = Runtime::new().unwrap().block_on(list_objects(prefix));
-    match objects {
-        Err(e) => return Err(e),
-        Ok(objects) => {
-            for object in objects {
-                match object.common_prefixes {
-                    None => continue,
-                    Some(common_prefixes) => {
-                        for common_prefix in common_prefixes {
-                            output.push(common_prefix.prefix);
-                        }
+    /// Folders directly below a prefix, each ending with `/`.
+    pub async fn list_folders(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut output: Vec<String> = Vec::new();
+        for object in self.list_objects(prefix).await? {
+            match object.common_prefixes {
+                None => continue,
+                Some(common_prefixes) => {
+                    for common_prefix in common_prefixes {
+                        output.push(common_prefix.prefix);
                     }
                 }
             }
         }
+        Ok(output)
+    }
+
+    pub fn list_folders_sync(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        self.block_on(self.list_folders(prefix))
     }
-    Ok(output)
 }
 
 fn convert_to_artifact_tree(prefix: &str, objects: Vec<ListBucketResult>) -> ArtifactNode {
@@ -110,7 +127,7 @@ fn convert_to_artifact_tree(prefix: &str, objects: Vec<ListBucketResult>) -> Art
             build_artifact_tree(&mut root, &parts, 0);
         }
     }
-    return root;
+    root
 }
 
 pub fn print_tree_list(prefix: &str, objects: Vec<ListBucketResult>) {
@@ -128,76 +145,320 @@ pub fn print_flat_list(prefix: &str, objects: Vec<ListBucketResult>) {
     }
 }
 
-async fn find_commit_hash_in(
-    timestamp_folder: &str,
+/// Number of timestamp folders listed at the same time when looking for a commit
+pub(crate) const SCAN_CONCURRENCY: usize = 16;
+
+/// Commit folder matching a commit hash, as `(tag, timestamp folder, commit folder)`
+pub(crate) type CommitMatch<T> = (T, String, String);
+
+/// Look for commit folders matching a commit hash in timestamp folders, each tagged with e.g. its
+/// branch. The folders are listed `SCAN_CONCURRENCY` at a time, newest first, and the matches are
+/// returned as `(tag, timestamp folder, commit folder)`.
+///
+/// With `stop_at_first`, scanning stops at the first, newest, match and the listings still
+/// running are cancelled.
+pub(crate) async fn scan_timestamp_folders<T: Clone>(
+    client: &S3Client,
+    timestamp_folders: Vec<(T, String)>,
     commit_hash: &str,
-) -> Result<Option<String>, Box<dyn Error>> {
-    let commit_folders = list_objects(timestamp_folder).await?;
-    for commit_folder in commit_folders {
-        match commit_folder.common_prefixes {
-            None => continue,
-            Some(common_prefixes) => {
-                for commit in common_prefixes {
-                    let commit_hash_short = commit.prefix.rsplit("/").nth(1).unwrap();
-                    if commit_hash.contains(commit_hash_short) {
-                        println!("Found an exact match for: {}", commit_hash_short);
-                        return Ok(Some(commit.prefix));
-                    }
-                    if commit_hash_short.contains(commit_hash) {
-                        println!("Did you mean --commit-hash {}?", commit_hash);
-                        println!("Found one match {}", commit.prefix);
-                        return Err("Did not find an exact match".into());
-                    }
-                }
+    stop_at_first: bool,
+) -> Result<Vec<CommitMatch<T>>, Box<dyn Error>> {
+    let list_folders = |folder: String| async move { client.list_folders(&folder).await };
+    scan_with(timestamp_folders, commit_hash, stop_at_first, list_folders).await
+}
+
+/// [`scan_timestamp_folders`], listing the commit folders of a timestamp folder with
+/// `list_folders`.
+async fn scan_with<T, L, F>(
+    mut timestamp_folders: Vec<(T, String)>,
+    commit_hash: &str,
+    stop_at_first: bool,
+    list_folders: L,
+) -> Result<Vec<CommitMatch<T>>, Box<dyn Error>>
+where
+    T: Clone,
+    L: Fn(String) -> F,
+    F: Future<Output = Result<Vec<String>, Box<dyn Error>>>,
+{
+    timestamp_folders.sort_by_cached_key(|(_, folder)| {
+        let timestamp = last_segment(folder);
+        Reverse((parse_timestamp(timestamp), timestamp.to_string()))
+    });
+    let mut scans = stream::iter(timestamp_folders)
+        .map(|(tag, timestamp_folder)| {
+            let listing = list_folders(timestamp_folder.clone());
+            async move { (tag, timestamp_folder, listing.await) }
+        })
+        .buffered(SCAN_CONCURRENCY);
+    let mut found = Vec::new();
+    while let Some((tag, timestamp_folder, commit_folders)) = scans.next().await {
+        for commit_folder in commit_folders? {
+            if matches_commit_hash(last_segment(&commit_folder), commit_hash) {
+                found.push((tag.clone(), timestamp_folder.clone(), commit_folder));
             }
         }
+        if stop_at_first && !found.is_empty() {
+            break;
+        }
     }
-    return Ok(None);
+    Ok(found)
 }
 
+/// Find the commit folder of a commit hash, which may be abbreviated, in the timestamp folders
+/// below `prefix`. If the commit was built several times, the newest build is returned.
 pub async fn find_artifact_with_commit_hash(
+    client: &S3Client,
     prefix: &str,
     commit_hash: &str,
 ) -> Result<String, Box<dyn Error>> {
-    let folders_under_prefix = list_objects(prefix).await?;
-    for folder_under_prefix in folders_under_prefix {
-        match folder_under_prefix.common_prefixes {
-            None => continue,
-            Some(common_prefixes) => {
-                for timestamp_folder in common_prefixes {
-                    let found_commit_folder =
-                        find_commit_hash_in(timestamp_folder.prefix.as_str(), commit_hash).await?;
-                    match found_commit_folder {
-                        None => continue,
-                        Some(artifact_path) => {
-                            return Ok(artifact_path);
-                        }
-                    }
+    validate_commit_hash(commit_hash)?;
+    let timestamp_folders = client
+        .list_folders(prefix)
+        .await?
+        .into_iter()
+        .map(|folder| ((), folder))
+        .collect();
+    // A full commit hash can't be ambiguous, so its newest match is the result
+    let stop_at_first = is_full_commit_hash(commit_hash);
+    let candidates: Vec<String> =
+        scan_timestamp_folders(client, timestamp_folders, commit_hash, stop_at_first)
+            .await?
+            .into_iter()
+            .map(|(_, _, commit_folder)| commit_folder)
+            .collect();
+    check_unique_commit(commit_hash, &candidates)?;
+    // Matches are ordered newest first
+    Ok(candidates.into_iter().next().unwrap())
+}
+
+/// Commit folder of a build found by its commit hash.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CommitArtifact {
+    pub branch: String,
+    pub timestamp: String,
+    pub commit_hash: String,
+    /// Path to the commit folder, ending with `/`
+    pub path: String,
+}
+
+impl From<CommitMatch<String>> for CommitArtifact {
+    /// Commit folder found by [`scan_timestamp_folders`] in the timestamp folders of a branch.
+    fn from((branch, timestamp_folder, path): CommitMatch<String>) -> Self {
+        CommitArtifact {
+            branch,
+            timestamp: last_segment(&timestamp_folder).to_string(),
+            commit_hash: last_segment(&path).to_string(),
+            path,
+        }
+    }
+}
+
+/// Timestamp folders of master, every release branch and every other branch, with their branch.
+pub(crate) async fn branch_timestamp_folders(
+    client: &S3Client,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let branches = branch_names(client).await?;
+    let mut listings = stream::iter(branches)
+        .map(|branch_name| async move {
+            let timestamp_folders = client
+                .list_folders(&branch_commit_folder(&branch_name))
+                .await;
+            (branch_name, timestamp_folders)
+        })
+        .buffered(SCAN_CONCURRENCY);
+    let mut timestamp_folders = Vec::new();
+    while let Some((branch_name, folders)) = listings.next().await {
+        for folder in folders? {
+            timestamp_folders.push((branch_name.clone(), folder));
+        }
+    }
+    Ok(timestamp_folders)
+}
+
+/// Find the commit folders of a commit hash, which may be abbreviated, in master, every release
+/// branch and every other branch, newest first.
+pub async fn find_commit_artifacts_in_all_branches(
+    client: &S3Client,
+    commit_hash: &str,
+) -> Result<Vec<CommitArtifact>, Box<dyn Error>> {
+    validate_commit_hash(commit_hash)?;
+    let timestamp_folders = branch_timestamp_folders(client).await?;
+    let artifacts: Vec<CommitArtifact> =
+        scan_timestamp_folders(client, timestamp_folders, commit_hash, false)
+            .await?
+            .into_iter()
+            .map(CommitArtifact::from)
+            .collect();
+    let candidates: Vec<String> = artifacts.iter().map(|a| a.path.clone()).collect();
+    check_unique_commit(commit_hash, &candidates)?;
+    Ok(artifacts)
+}
+
+/// End of the range to ask rust-s3 for to read bytes `start` to `end` (inclusive) of a file of
+/// `size` bytes. rust-s3 can't request a single byte, so it's read with the byte after it, or
+/// until the end of the file if it's the last one, and the extra byte dropped afterwards.
+fn range_request_end(start: u64, end: u64, size: u64) -> Option<u64> {
+    if end > start {
+        Some(end)
+    } else if start + 1 < size {
+        Some(start + 1)
+    } else {
+        None
+    }
+}
+
+/// Size of the buffer between the download of a streamed file and its reader
+const STREAM_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Content of a file, downloaded while it's read so that large files don't have to fit in
+/// memory. Ends early if the download fails.
+pub struct ObjectReader {
+    content: Take<DuplexStream>,
+    runtime: Handle,
+}
+
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.runtime.block_on(self.content.read(buf))
+    }
+}
+
+impl S3Client {
+    pub async fn get_object(&self, artifact_file: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let response = self
+            .retried("get", artifact_file, || {
+                self.bucket().get_object(artifact_file)
+            })
+            .await?;
+        if response.status_code() != 200 {
+            return Err(format!("Failed to download artifact: {}", response.status_code()).into());
+        }
+        Ok(response.to_vec())
+    }
+
+    pub fn get_object_sync(&self, artifact_file: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.block_on(self.get_object(artifact_file))
+    }
+
+    pub async fn put_object(&self, key: &str, content: &[u8]) -> Result<(), Box<dyn Error>> {
+        timed("put", self.bucket().put_object(key, content)).await?;
+        Ok(())
+    }
+
+    /// Url to download a file without credentials, valid for `expiry_secs` seconds (at most a
+    /// week).
+    pub fn presign_get(
+        &self,
+        artifact_file: &str,
+        expiry_secs: u32,
+    ) -> Result<String, Box<dyn Error>> {
+        let url = self
+            .bucket()
+            .presign_get(artifact_file, expiry_secs, None)?;
+        Ok(url)
+    }
+
+    /// Stream bytes `start` to `end` (inclusive) of a file of `size` bytes, or the whole file
+    /// without a range. The file is downloaded while it's read, so the reader must not be read
+    /// from async code.
+    pub fn object_reader(
+        &self,
+        artifact_file: &str,
+        range: Option<(u64, u64)>,
+        size: u64,
+    ) -> ObjectReader {
+        let length = match range {
+            Some((start, end)) => end - start + 1,
+            None => size,
+        };
+        let (mut writer, reader) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+        let bucket = self.bucket().clone();
+        let key = artifact_file.to_string();
+        self.runtime().spawn(async move {
+            let result = match range {
+                Some((start, end)) => {
+                    let range_end = range_request_end(start, end, size);
+                    let download =
+                        bucket.get_object_range_to_writer(&key, start, range_end, &mut writer);
+                    timed("get_range", download).await
                 }
+                None => timed("get", bucket.get_object_to_writer(&key, &mut writer)).await,
+            };
+            // Also when the reader is dropped early, e.g. because the client went away
+            if let Err(e) = result {
+                tracing::warn!("Stopped streaming {}: {}", key, e);
             }
+        });
+        ObjectReader {
+            content: reader.take(length),
+            runtime: self.runtime().handle().clone(),
+        }
+    }
+
+    pub async fn object_size(&self, artifact_file: &str) -> Result<u64, Box<dyn Error>> {
+        let (head, _) = self
+            .retried("head", artifact_file, || {
+                self.bucket().head_object(artifact_file)
+            })
+            .await?;
+        match head.content_length {
+            Some(size) => Ok(size as u64),
+            None => Err(format!("Unknown size of {}", artifact_file).into()),
         }
     }
-    Err(format!("Did not find any artifact with commit hash {}", commit_hash).into())
+
+    pub fn object_size_sync(&self, artifact_file: &str) -> Result<u64, Box<dyn Error>> {
+        self.block_on(self.object_size(artifact_file))
+    }
+
+    async fn download_artifact(
+        &self,
+        artifact_file: &str,
+        destination_folder: &Path,
+        progress: &FileProgress<'_>,
+    ) -> Result<(), Box<dyn Error>> {
+        // TODO: Replace string concatenation with std::fs
+        let destination = format!(
+            "{}/{}",
+            destination_folder.display(),
+            artifact_file.rsplit("/").next().unwrap()
+        );
+        // Local errors like a full disk aren't worth retrying, so the file is only created once
+        let file = tokio::fs::File::create(&destination).await?;
+        let file = &file;
+        self.retried("get", artifact_file, move || async move {
+            // A failed attempt may have written part of the file already
+            progress.restart();
+            let mut file = file.try_clone().await?;
+            file.set_len(0).await?;
+            file.rewind().await?;
+            let mut writer = progress.writer(file);
+            self.bucket()
+                .get_object_to_writer(artifact_file, &mut writer)
+                .await?;
+            writer.flush().await?;
+            Ok(())
+        })
+        .await?;
+        Ok(())
+    }
 }
 
-async fn download_artifact(
-    artifact_file: &str,
-    destination_folder: &PathBuf,
-) -> Result<(), Box<dyn Error>> {
-    let bucket = get_bucket()?;
-    let response = bucket.get_object(artifact_file).await?;
-    if response.status_code() != 200 {
-        return Err(format!("Failed to download artifact: {}", response.status_code()).into());
-    }
-    // TODO: Replace string concatenation with std::fs
-    let destination = format!(
-        "{}/{}",
-        destination_folder.display().to_string(),
-        artifact_file.rsplit("/").nth(0).unwrap()
-    );
-    let mut buffer = File::create(&destination)?;
-    buffer.write_all(&response.as_slice())?;
-    Ok(())
+/// Presigned urls of every file below `prefix`, with the path of the file relative to it.
+pub fn presign_folder(
+    client: &S3Client,
+    prefix: &str,
+    expiry_secs: u32,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut links = Vec::new();
+    for object in client.list_all_objects_sync(prefix)? {
+        for file in object.contents {
+            let url = client.presign_get(&file.key, expiry_secs)?;
+            let file_path_from_prefix = file.key.strip_prefix(prefix).unwrap();
+            links.push((file_path_from_prefix.to_string(), url));
+        }
+    }
+    Ok(links)
 }
 
 fn move_from_temp_to_dest(
@@ -205,7 +466,7 @@ fn move_from_temp_to_dest(
     destination_folder: &Path,
 ) -> Result<(), Box<dyn Error>> {
     if temporary_folder == destination_folder {
-        println!(
+        eprintln!(
             "Artifacts successfully downloaded to {}",
             destination_folder.display()
         );
@@ -214,7 +475,7 @@ fn move_from_temp_to_dest(
 
     // If destination folder exists, throw exception
     if destination_folder.exists() {
-        println!(
+        eprintln!(
             "Destination folder {} already exists and will be overwritten.",
             destination_folder.display()
         );
@@ -236,7 +497,7 @@ fn move_from_temp_to_dest(
         },
     ) {
         Ok(_) => {
-            println!(
+            eprintln!(
                 "Artifacts successfully downloaded to {}",
                 destination_folder.display()
             );
@@ -246,16 +507,26 @@ fn move_from_temp_to_dest(
     }
 }
 
-pub fn download_artifacts_sync(
+/// Download every file below `artifact_path_str` to `destination_path_str`, keeping the folder
+/// hierarchy below it, showing the progress and how long it took. Returns the paths of the
+/// downloaded files in the destination, which may also hold files of earlier downloads.
+pub async fn download_artifacts(
+    client: &S3Client,
     artifact_path_str: &str,
     destination_path_str: &str,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
     let mut temporary_folder = std::env::temp_dir();
     temporary_folder.push(destination_path_str);
     let artifact_path = Path::new(artifact_path_str);
+    let destination_path = Path::new(destination_path_str);
+    let mut downloaded = Vec::new();
 
-    let rt = Runtime::new().unwrap();
-    let objects = list_all_objects(&artifact_path_str)?;
+    let objects = client.list_all_objects(artifact_path_str).await?;
+    let files = objects.iter().flat_map(|object| &object.contents);
+    let progress = DownloadProgress::new(
+        files.clone().count() as u64,
+        files.map(|file| file.size).sum(),
+    );
     for object in objects {
         match object.prefix {
             None => continue,
@@ -265,17 +536,142 @@ pub fn download_artifacts_sync(
                 let folder_to_create = temporary_folder.join(artifact_folder);
                 std::fs::create_dir_all(&folder_to_create)?;
 
-                object
-                    .contents
-                    .iter()
-                    .map(|artifact_object: &Object| {
-                        println!("Downloading file: {:?}", &artifact_object.key);
-                        rt.block_on(download_artifact(&artifact_object.key, &folder_to_create))
-                    })
-                    .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+                for artifact_object in &object.contents {
+                    let file_progress =
+                        progress.start_file(&artifact_object.key, artifact_object.size);
+                    client
+                        .download_artifact(&artifact_object.key, &folder_to_create, &file_progress)
+                        .await?;
+                    file_progress.finish();
+                    let file_name = artifact_object.key.rsplit('/').next().unwrap();
+                    downloaded.push(destination_path.join(artifact_folder).join(file_name));
+                }
             }
         }
     }
-    let destination_path = Path::new(destination_path_str);
-    return move_from_temp_to_dest(temporary_folder.as_path(), destination_path);
+    progress.finish();
+    move_from_temp_to_dest(temporary_folder.as_path(), destination_path)?;
+    Ok(downloaded)
+}
+
+pub fn download_artifacts_sync(
+    client: &S3Client,
+    artifact_path_str: &str,
+    destination_path_str: &str,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    client.block_on(download_artifacts(
+        client,
+        artifact_path_str,
+        destination_path_str,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const MASTER: &str = "success/master/sdk/commit/";
+
+    /// Scan timestamp folders of master holding the given commits, counting the listings.
+    fn scan(
+        folders: &[(&str, &[&str])],
+        commit_hash: &str,
+        stop_at_first: bool,
+    ) -> (Vec<String>, usize) {
+        let commits: HashMap<String, Vec<String>> = folders
+            .iter()
+            .map(|(timestamp, hashes)| {
+                let folder = format!("{}{}/", MASTER, timestamp);
+                let commit_folders = hashes
+                    .iter()
+                    .map(|hash| format!("{}{}/", folder, hash))
+                    .collect();
+                (folder, commit_folders)
+            })
+            .collect();
+        let listings = AtomicUsize::new(0);
+        let list_folders = |folder: String| {
+            listings.fetch_add(1, Ordering::Relaxed);
+            let commit_folders = commits[&folder].clone();
+            async move { Ok(commit_folders) }
+        };
+        let timestamp_folders = commits.keys().map(|folder| ((), folder.clone())).collect();
+        let found = futures::executor::block_on(scan_with(
+            timestamp_folders,
+            commit_hash,
+            stop_at_first,
+            list_folders,
+        ))
+        .unwrap();
+        let paths = found.into_iter().map(|(_, _, path)| path).collect();
+        (paths, listings.into_inner())
+    }
+
+    #[test]
+    fn matches_are_returned_newest_first() {
+        let folders: &[(&str, &[&str])] = &[
+            ("20240502-1000", &["aaaa1111"]),
+            ("garbage", &["aaaa1111"]),
+            ("2024-05-03T10:00:00Z", &["aaaa1111", "bbbb2222"]),
+            ("1714471200", &["aaaa1111"]),
+            ("2024-05-01T12:00:00Z", &["cccc3333"]),
+        ];
+        let (paths, listings) = scan(folders, "aaaa", false);
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}2024-05-03T10:00:00Z/aaaa1111/", MASTER),
+                format!("{}20240502-1000/aaaa1111/", MASTER),
+                format!("{}1714471200/aaaa1111/", MASTER),
+                // Folders whose name isn't a timestamp come last
+                format!("{}garbage/aaaa1111/", MASTER),
+            ]
+        );
+        assert_eq!(listings, folders.len());
+    }
+
+    #[test]
+    fn scan_stops_at_the_first_match() {
+        let days: Vec<String> = (1..=28).map(|day| format!("2024-05-{:02}", day)).collect();
+        let mut folders: Vec<(&str, &[&str])> = days
+            .iter()
+            .map(|day| (day.as_str(), &["aaaa1111"][..]))
+            .collect();
+        folders[20].1 = &["bbbb2222"];
+
+        let (paths, listings) = scan(&folders, "bbbb", true);
+        assert_eq!(paths, vec![format!("{}2024-05-21/bbbb2222/", MASTER)]);
+        // The folders up to the match and the ones listed at the same time, not all 28
+        assert!(listings <= 8 + SCAN_CONCURRENCY, "{}", listings);
+        assert!(listings < folders.len(), "{}", listings);
+
+        let (paths, listings) = scan(&folders, "aaaa", true);
+        assert_eq!(paths, vec![format!("{}2024-05-28/aaaa1111/", MASTER)]);
+        assert_eq!(listings, SCAN_CONCURRENCY);
+
+        let (paths, listings) = scan(&folders, "aaaa", false);
+        assert_eq!(paths.len(), 27);
+        assert_eq!(listings, folders.len());
+    }
+
+    #[test]
+    fn range_request_end_asks_for_the_range() {
+        assert_eq!(range_request_end(0, 99, 1000), Some(99));
+        assert_eq!(range_request_end(900, 999, 1000), Some(999));
+    }
+
+    #[test]
+    fn single_byte_is_read_with_the_next_one() {
+        assert_eq!(range_request_end(0, 0, 1000), Some(1));
+        assert_eq!(range_request_end(998, 998, 1000), Some(999));
+    }
+
+    #[test]
+    fn last_byte_is_read_until_the_end() {
+        assert_eq!(range_request_end(999, 999, 1000), None);
+        assert_eq!(range_request_end(0, 0, 1), None);
+    }
 }
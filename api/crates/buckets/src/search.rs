@@ -0,0 +1,30 @@
+use crate::client::S3Client;
+
+use glob::Pattern;
+
+use query::{is_glob, matches_query};
+
+use s3::serde_types::Object;
+
+use std::error::Error;
+
+/// Recursively list every file under `prefix` and keep the ones matching `query`.
+pub fn search_objects(
+    client: &S3Client,
+    prefix: &str,
+    query: &str,
+) -> Result<Vec<Object>, Box<dyn Error>> {
+    if is_glob(query) {
+        Pattern::new(query).map_err(|e| format!("Invalid search pattern '{}': {}", query, e))?;
+    }
+    let objects = client.list_all_objects_sync(prefix)?;
+    let found = objects
+        .into_iter()
+        .flat_map(|object| object.contents)
+        .filter(|file| {
+            let relative_path = file.key.strip_prefix(prefix).unwrap_or(&file.key);
+            matches_query(relative_path, query)
+        })
+        .collect();
+    Ok(found)
+}
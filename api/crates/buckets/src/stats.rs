@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Upper bounds in seconds of the S3 call latency histogram buckets.
+pub const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Latency histogram of one kind of S3 call, e.g. `list` or `get`.
+#[derive(Clone, Default)]
+pub struct S3CallStats {
+    pub count: u64,
+    pub errors: u64,
+    pub sum_secs: f64,
+    /// Calls that took at most the matching `LATENCY_BUCKETS` bound, not cumulative
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+}
+
+static S3_CALL_STATS: Mutex<BTreeMap<&'static str, S3CallStats>> = Mutex::new(BTreeMap::new());
+
+fn record(operation: &'static str, secs: f64, failed: bool) {
+    let mut stats = S3_CALL_STATS.lock().unwrap();
+    let call_stats = stats.entry(operation).or_default();
+    call_stats.count += 1;
+    call_stats.sum_secs += secs;
+    if failed {
+        call_stats.errors += 1;
+    }
+    if let Some(i) = LATENCY_BUCKETS.iter().position(|bound| secs <= *bound) {
+        call_stats.buckets[i] += 1;
+    }
+}
+
+/// Run an S3 call and record how long it took.
+pub(crate) async fn timed<T, E, F>(operation: &'static str, call: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let result = call.await;
+    let secs = start.elapsed().as_secs_f64();
+    tracing::debug!(operation, secs, failed = result.is_err(), "S3 call");
+    record(operation, secs, result.is_err());
+    result
+}
+
+/// Statistics of every S3 call made by this process so far, by operation.
+pub fn s3_call_stats() -> BTreeMap<&'static str, S3CallStats> {
+    S3_CALL_STATS.lock().unwrap().clone()
+}
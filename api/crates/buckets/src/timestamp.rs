@@ -0,0 +1,105 @@
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+fn number(digits: &str) -> Option<u32> {
+    digits.parse().ok()
+}
+
+/// Parse the name of a timestamp folder as a date in UTC.
+///
+/// Accepts unix timestamps in seconds, and dates with an optional time of day in any notation
+/// with the digits in order year, month, day, hour, minute and second, e.g.
+/// `2024-05-03T12:34:56Z`, `2024-05-03_12-34-56` or `20240503-1234`. Fractions of a second and
+/// time zone offsets are ignored.
+pub fn parse_timestamp(name: &str) -> Option<OffsetDateTime> {
+    let name = name.trim_end_matches('/');
+    if name.len() == 10 && name.bytes().all(|b| b.is_ascii_digit()) {
+        return OffsetDateTime::from_unix_timestamp(name.parse().ok()?).ok();
+    }
+    let digits: String = name.chars().filter(|c| c.is_ascii_digit()).collect();
+    // Fractions of a second and offsets come after the seconds and are left out
+    let (date, time) = match digits.len() {
+        8 => (&digits[..8], "0000"),
+        12 => (&digits[..8], &digits[8..12]),
+        len if len >= 14 => (&digits[..8], &digits[8..14]),
+        _ => return None,
+    };
+    let date = Date::from_calendar_date(
+        number(&date[..4])? as i32,
+        Month::try_from(number(&date[4..6])? as u8).ok()?,
+        number(&date[6..8])? as u8,
+    )
+    .ok()?;
+    let second = if time.len() == 6 { &time[4..6] } else { "00" };
+    let time = Time::from_hms(
+        number(&time[..2])? as u8,
+        number(&time[2..4])? as u8,
+        number(second)? as u8,
+    )
+    .ok()?;
+    Some(PrimitiveDateTime::new(date, time).assume_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(date: (i32, u8, u8), time: (u8, u8, u8)) -> Option<OffsetDateTime> {
+        let date = Date::from_calendar_date(date.0, Month::try_from(date.1).unwrap(), date.2);
+        let time = Time::from_hms(time.0, time.1, time.2).unwrap();
+        Some(PrimitiveDateTime::new(date.unwrap(), time).assume_utc())
+    }
+
+    #[test]
+    fn iso_8601() {
+        let expected = utc((2024, 5, 3), (12, 34, 56));
+        assert_eq!(parse_timestamp("2024-05-03T12:34:56Z"), expected);
+        assert_eq!(parse_timestamp("2024-05-03T12:34:56Z/"), expected);
+    }
+
+    #[test]
+    fn other_notations() {
+        assert_eq!(
+            parse_timestamp("2024-05-03_12-34-56"),
+            utc((2024, 5, 3), (12, 34, 56))
+        );
+        assert_eq!(
+            parse_timestamp("20240503-1234"),
+            utc((2024, 5, 3), (12, 34, 0))
+        );
+        assert_eq!(parse_timestamp("2024-05-03"), utc((2024, 5, 3), (0, 0, 0)));
+    }
+
+    #[test]
+    fn unix_timestamp() {
+        assert_eq!(
+            parse_timestamp("1714739696"),
+            utc((2024, 5, 3), (12, 34, 56))
+        );
+    }
+
+    #[test]
+    fn fractions_and_offsets_are_ignored() {
+        let expected = utc((2024, 5, 3), (12, 34, 56));
+        assert_eq!(parse_timestamp("2024-05-03T12:34:56.789Z"), expected);
+        // The time is read as UTC, whatever the offset says
+        assert_eq!(parse_timestamp("2024-05-03T12:34:56+02:00"), expected);
+        assert_eq!(parse_timestamp("2024-05-03T12:34:56.5-07:00"), expected);
+    }
+
+    #[test]
+    fn invalid_timestamps() {
+        for name in [
+            "",
+            "latest",
+            "garbage",
+            "2024-05",
+            "2024-05-03T12",
+            "2024-13-03",
+            "2024-02-30",
+            "2024-05-03T25:00",
+            "2024-05-03T12:60:00",
+        ] {
+            assert_eq!(parse_timestamp(name), None, "{}", name);
+        }
+    }
+}
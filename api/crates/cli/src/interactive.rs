@@ -19,16 +19,14 @@ fn prompt(text: &str) -> String {
 
     let value = response.trim_end().to_string();
     std::env::set_var(text, &value);
-    return value;
+    value
 }
 
-
-fn read_from_env_or_prompt(env_variable: &str) -> String
-{
+fn read_from_env_or_prompt(env_variable: &str) -> String {
     let env_value = std::env::var(env_variable);
     match env_value {
         Ok(value) => value,
-        Err(_) => prompt(env_variable)
+        Err(_) => prompt(env_variable),
     }
 }
 
@@ -56,11 +54,15 @@ pub struct S3Config {
 }
 
 pub fn read_credentials() -> S3Config {
+    read_credentials_with(None, None)
+}
+
+/// Like [`read_credentials`], but using the given hostname and bucket instead of reading them.
+pub fn read_credentials_with(hostname: Option<String>, bucket_name: Option<String>) -> S3Config {
     S3Config {
-        hostname: read_s3_hostname(),
+        hostname: hostname.unwrap_or_else(read_s3_hostname),
         access_key: read_s3_access_key(),
         secret_key: read_s3_secret_key(),
-        bucket_name: read_s3_bucket_name(),
+        bucket_name: bucket_name.unwrap_or_else(read_s3_bucket_name),
     }
 }
-
@@ -1,5 +1,8 @@
 mod interactive;
+mod logging;
 
 // Export functions from library and other modules within this library from here
-pub use crate::interactive::S3Config;
 pub use crate::interactive::read_credentials;
+pub use crate::interactive::read_credentials_with;
+pub use crate::interactive::S3Config;
+pub use crate::logging::init_tracing;
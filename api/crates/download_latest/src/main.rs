@@ -1,11 +1,21 @@
+use buckets::dated_timestamp_folders;
 use buckets::download_artifacts_sync;
-use buckets::list_folders_in_prefix;
+use buckets::extract_archives;
+use buckets::find_build_in;
+use buckets::missing_files;
+use buckets::parse_timestamp;
+use buckets::presign_folder;
+use buckets::S3Client;
+use buckets::{BuildSelection, LatestBuild, PathTemplate};
+use buckets::{DEFAULT_TEMPLATE, WINDOWS_TEMPLATE};
 
 use clap::{Parser, ValueEnum};
 
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
+use time::OffsetDateTime;
+
 #[derive(ValueEnum, Clone, Default, Debug, PartialEq)]
 enum Architecture {
     #[default]
@@ -28,6 +38,10 @@ impl Display for Architecture {
     about = "Download latest successful artifacts for given branch from artifact storage."
 )]
 struct Args {
+    /// Bucket to look in instead of S3_BUCKET
+    #[arg(long)]
+    bucket: Option<String>,
+
     /// Name of the branch
     #[arg(short, long, default_value = "master")]
     branch: String,
@@ -35,77 +49,359 @@ struct Args {
     /// Target architecture of artifacts
     #[arg(short, long, default_value = "amd64")]
     architecture: Architecture,
+
+    /// Target operating system or distribution of artifacts, e.g. ubuntu-22.04 or windows.
+    /// Defaults to the one of this machine
+    #[arg(short, long)]
+    os: Option<String>,
+
+    /// Build configuration, e.g. Debug. Defaults to Release, or Installers on windows
+    #[arg(short, long)]
+    config: Option<String>,
+
+    /// Product whose artifacts to download
+    #[arg(short, long, default_value = "sdk")]
+    product: String,
+
+    /// Layout of the artifact folders in the bucket, with the placeholders {branch_group},
+    /// {branch}, {product}, {timestamp}, {hash}, {os}, {arch} and {config}. Defaults to the
+    /// layout of the sdk for the operating system
+    #[arg(short, long)]
+    template: Option<String>,
+
+    /// File the artifacts must contain for a build to be complete, relative to the artifact
+    /// folder, e.g. a completion marker. Globs like '*.deb' are allowed. Can be repeated
+    #[arg(short, long)]
+    require: Vec<String>,
+
+    /// Number of older builds to try when the newest one is incomplete
+    #[arg(long, default_value = "10")]
+    fallback: usize,
+
+    /// Download the n-th newest complete build instead, e.g. 2 for the previous one
+    #[arg(short, long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    nth: u64,
+
+    /// Only consider builds with an earlier timestamp, e.g. 2024-05-03 or 2024-05-03T12:00:00Z
+    #[arg(long)]
+    before: Option<String>,
+
+    /// List the commits of the newest builds with their timestamps instead of downloading, 20
+    /// unless a number is given
+    #[arg(short, long, num_args = 0..=1, default_missing_value = "20")]
+    list: Option<usize>,
+
+    /// Folder to download the artifacts to
+    #[arg(short, long, default_value = "artifacts")]
+    destination: String,
+
+    /// Unpack the downloaded .zip, .tar.gz and .tar.xz archives next to them
+    #[arg(short, long, conflicts_with_all = ["print_path", "print_urls"])]
+    extract: bool,
+
+    /// Delete the archives once they are unpacked
+    #[arg(long, requires = "extract")]
+    delete_archives: bool,
+
+    /// Only print the folder of the artifacts in the bucket instead of downloading
+    #[arg(long, conflicts_with = "print_urls")]
+    print_path: bool,
+
+    /// Only print presigned links to the artifacts, which work without S3 credentials, instead
+    /// of downloading
+    #[arg(long)]
+    print_urls: bool,
+
+    /// Seconds the links of --print-urls are valid for, at most a week
+    #[arg(long, default_value = "86400")]
+    expiry_secs: u32,
+}
+
+/// What to do with the artifacts once found
+enum Output {
+    /// Download to a folder, extracting archives (and deleting them) if asked to
+    Download {
+        destination: String,
+        extract: bool,
+        delete_archives: bool,
+    },
+    PrintPath,
+    PrintUrls(u32),
+}
+
+struct Target {
+    os: String,
+    architecture: Architecture,
+    config: String,
+    product: String,
 }
 
-fn os_name() -> Result<String, Box<dyn Error>> {
+fn host_os_name() -> Result<String, Box<dyn Error>> {
     let os = std::env::consts::OS;
     match os {
         "linux" => Ok("ubuntu-20.04".to_string()),
         "windows" => Ok("windows".to_string()),
-        &_ => Err("Unsupported operating system".into()),
+        &_ => Err("Unsupported operating system, choose one with --os".into()),
     }
 }
 
-fn artifact_path(
-    architecture: Architecture,
-    latest_commit_hash: &str,
-) -> Result<String, Box<dyn Error>> {
-    let os = os_name()?;
-    match os.as_str() {
-        "ubuntu-20.04" => {
-            return Ok(format!(
-                "{}{}/{}/Release/",
-                latest_commit_hash, os, architecture,
-            ));
-        }
+fn default_template(os: &str, architecture: &Architecture) -> Result<&'static str, Box<dyn Error>> {
+    match os {
         "windows" => {
-            if architecture != Architecture::Amd64 {
+            if *architecture != Architecture::Amd64 {
                 return Err("Unsupported architecture for windows".into());
             }
-            // Windows artifacts don't have architecture in the path
-            return Ok(format!("{}{}/Installers/", latest_commit_hash, os,));
+            Ok(WINDOWS_TEMPLATE)
         }
-        &_ => Err("Unsupported operating system".into()),
+        _ => Ok(DEFAULT_TEMPLATE),
     }
 }
 
-fn bucket_to_search(branch_name: &str) -> String {
-    if branch_name == "master" {
-        format!("success/{}/sdk/commit/", branch_name)
-    } else if branch_name.starts_with("release-") {
-        format!("success/release/{}/sdk/commit/", branch_name)
-    } else {
-        format!("success/other/{}/sdk/commit/", branch_name)
+fn default_config(os: &str) -> &'static str {
+    match os {
+        "windows" => "Installers",
+        _ => "Release",
     }
 }
 
-fn latest_artifact(branch_name: &str, architecture: Architecture) -> Result<(), Box<dyn Error>> {
-    let path_to_search = bucket_to_search(&branch_name);
-    let objects = list_folders_in_prefix(&path_to_search)?;
-    if objects.is_empty() {
-        return Err(format!(
-            "No successful sdk commit artifacts found for branch {}",
-            branch_name
-        )
-        .into());
+/// Check folder by folder that `relative_folder` exists below `base_folder`, to tell which part
+/// of the target isn't available rather than downloading nothing.
+fn check_folder_exists(
+    client: &S3Client,
+    base_folder: &str,
+    relative_folder: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut folder = base_folder.to_string();
+    for segment in relative_folder.split_terminator('/') {
+        let available: Vec<String> = client
+            .list_folders_sync(&folder)?
+            .iter()
+            .map(|child| child[folder.len()..].trim_end_matches('/').to_string())
+            .collect();
+        if !available.iter().any(|child| child == segment) {
+            return Err(format!(
+                "{} not found in {}, available: {}",
+                segment,
+                folder,
+                available.join(", ")
+            )
+            .into());
+        }
+        folder = format!("{}{}/", folder, segment);
     }
-    let latest_timestamp = objects.iter().max().unwrap();
-    let objects = list_folders_in_prefix(&latest_timestamp)?;
-    let latest_commit_hash = objects.iter().max().unwrap();
-    let artifacts_to_download = artifact_path(architecture, latest_commit_hash)?;
-    println!("Downloading artifacts from: {}", artifacts_to_download);
-    download_artifacts_sync(&artifacts_to_download, "artifacts")?;
     Ok(())
 }
 
+/// Check that the target exists in the newest build, so that a typo in e.g. `--os` fails with the
+/// available values instead of making every build look incomplete. Without any build, check that
+/// the branch and product exist instead.
+fn check_target_exists(
+    client: &S3Client,
+    branch_name: &str,
+    commit_folder: &str,
+    platform_folder: &str,
+    before: Option<OffsetDateTime>,
+) -> Result<(), Box<dyn Error>> {
+    let selection = BuildSelection {
+        before,
+        ..Default::default()
+    };
+    match find_build_in(client, branch_name, commit_folder, &selection, |_| Ok(true))? {
+        Some(newest) => check_folder_exists(client, &newest.path, platform_folder),
+        None => check_folder_exists(client, "", commit_folder),
+    }
+}
+
+/// Check that a build has artifacts for the target and all required files, telling why not.
+fn is_complete(
+    client: &S3Client,
+    build: &LatestBuild,
+    platform_folder: &str,
+    required: &[String],
+) -> Result<bool, Box<dyn Error>> {
+    if let Err(e) = check_folder_exists(client, &build.path, platform_folder) {
+        eprintln!("Skipping incomplete build {}: {}", build.path, e);
+        return Ok(false);
+    }
+    let missing = missing_files(
+        client,
+        &format!("{}{}", build.path, platform_folder),
+        required,
+    )?;
+    if !missing.is_empty() {
+        eprintln!(
+            "Skipping incomplete build {}: missing {}",
+            build.path,
+            missing.join(", ")
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn list_builds(
+    client: &S3Client,
+    commit_folder: &str,
+    before: Option<OffsetDateTime>,
+    count: usize,
+) -> Result<(), Box<dyn Error>> {
+    let timestamps = dated_timestamp_folders(client, commit_folder)?;
+    for (_, timestamp) in timestamps
+        .iter()
+        .filter(|(date, _)| before.is_none_or(|before| *date < before))
+        .take(count)
+    {
+        let timestamp_name = timestamp[commit_folder.len()..].trim_end_matches('/');
+        for commit in client.list_folders_sync(timestamp)? {
+            let commit_hash = commit[timestamp.len()..].trim_end_matches('/');
+            println!("{}\t{}\t{}", timestamp_name, commit_hash, commit);
+        }
+    }
+    Ok(())
+}
+
+fn latest_artifact(
+    client: &S3Client,
+    branch_name: &str,
+    commit_folder: &str,
+    platform_folder: &str,
+    selection: &BuildSelection,
+    required: &[String],
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    check_target_exists(
+        client,
+        branch_name,
+        commit_folder,
+        platform_folder,
+        selection.before,
+    )?;
+    let build = find_build_in(client, branch_name, commit_folder, selection, |build| {
+        is_complete(client, build, platform_folder, required)
+    })?;
+    let build = match build {
+        Some(build) => build,
+        None => {
+            return Err(format!(
+                "No matching complete build found in {} of branch {}",
+                commit_folder, branch_name
+            )
+            .into())
+        }
+    };
+    let artifacts_to_download = format!("{}{}", build.path, platform_folder);
+    match output {
+        Output::Download {
+            destination,
+            extract,
+            delete_archives,
+        } => {
+            println!("Downloading artifacts from: {}", artifacts_to_download);
+            let downloaded = download_artifacts_sync(client, &artifacts_to_download, destination)?;
+            if *extract {
+                let archives = extract_archives(&downloaded, *delete_archives)?;
+                println!("Extracted {} archives", archives.len());
+            }
+        }
+        Output::PrintPath => println!("{}", artifacts_to_download),
+        Output::PrintUrls(expiry_secs) => {
+            for (file_path, url) in presign_folder(client, &artifacts_to_download, *expiry_secs)? {
+                println!("{}\t{}", file_path, url);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Client for the bucket of `--bucket`, or of `S3_BUCKET` when not given.
+fn client_for(args: &Args) -> Result<S3Client, Box<dyn Error>> {
+    S3Client::from_env_for(None, args.bucket.as_deref())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
+    // Show warnings about skipped builds
+    cli::init_tracing();
     let args = Args::parse();
+    let client = client_for(&args)?;
     let branch_name = args.branch;
-    let architecture = args.architecture;
-    println!(
-        "Downloading latest artifacts for branch: '{}', architecture: '{}'",
-        branch_name, architecture
+    let os = match args.os {
+        Some(os) => os,
+        None => host_os_name()?,
+    };
+    let config = args
+        .config
+        .unwrap_or_else(|| default_config(&os).to_string());
+    let target = Target {
+        os,
+        architecture: args.architecture,
+        config,
+        product: args.product,
+    };
+    let template = match args.template {
+        Some(template) => PathTemplate::new(&template)?,
+        None => PathTemplate::new(default_template(&target.os, &target.architecture)?)?,
+    };
+    let commit_folder = template.commit_folder(&branch_name, &target.product);
+    let before = match args.before {
+        Some(before) => Some(
+            parse_timestamp(&before)
+                .ok_or_else(|| format!("Invalid date for --before: {}", before))?,
+        ),
+        None => None,
+    };
+    if let Some(count) = args.list {
+        return list_builds(&client, &commit_folder, before, count);
+    }
+
+    let output = if args.print_path {
+        Output::PrintPath
+    } else if args.print_urls {
+        Output::PrintUrls(args.expiry_secs)
+    } else {
+        Output::Download {
+            destination: args.destination,
+            extract: args.extract,
+            delete_archives: args.delete_archives,
+        }
+    };
+    // Keep the output of the print modes to what scripts ask for
+    eprintln!(
+        "Finding latest {} artifacts for branch: '{}', os: '{}', architecture: '{}', config: '{}'",
+        target.product, branch_name, target.os, target.architecture, target.config
     );
-    latest_artifact(&branch_name, architecture)?;
+    let platform_folder =
+        template.platform_folder(&target.os, &target.architecture.to_string(), &target.config);
+    let selection = BuildSelection {
+        nth: args.nth as usize,
+        before,
+        fallback: args.fallback,
+    };
+    latest_artifact(
+        &client,
+        &branch_name,
+        &commit_folder,
+        &platform_folder,
+        &selection,
+        &args.require,
+        &output,
+    )?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_option_is_enough() {
+        std::env::set_var("S3_HOSTNAME", "127.0.0.1:9000");
+        std::env::set_var("S3_ACCESSKEY", "access");
+        std::env::set_var("S3_SECRETKEY", "secret");
+        std::env::remove_var("S3_BUCKET");
+        let args = Args::parse_from(["download_latest", "--bucket", "other", "--list", "2"]);
+        let client = client_for(&args).unwrap();
+        assert_eq!(client.bucket_name(), "other");
+        // Asking for S3_BUCKET would have set it
+        assert!(std::env::var("S3_BUCKET").is_err());
+    }
+}
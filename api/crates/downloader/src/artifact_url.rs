@@ -0,0 +1,164 @@
+use percent_encoding::percent_decode_str;
+
+use url::Url;
+
+use std::error::Error;
+
+/// Where the artifacts given on the command line are. The endpoint and bucket are only set when
+/// the path is a URL naming them, otherwise the ones from the environment are used.
+#[derive(Debug, PartialEq)]
+pub struct ArtifactLocation {
+    /// Endpoint of the S3 API, e.g. `http://se-cluster-2:32000`
+    pub endpoint: Option<String>,
+    pub bucket: Option<String>,
+    /// Key, or prefix of a folder, in the bucket
+    pub path: String,
+}
+
+fn decoded_path(segments: &[&str]) -> Result<String, Box<dyn Error>> {
+    let mut path = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let segment = percent_decode_str(segment)
+            .decode_utf8()
+            .map_err(|e| format!("Invalid path segment {}: {}", segment, e))?;
+        path.push(segment.into_owned());
+    }
+    Ok(path.join("/"))
+}
+
+/// Parse an artifact path given as a key in the bucket or as one of these URLs:
+///
+/// - `s3://<bucket>/<key>`
+/// - `http(s)://<host>[:<port>]/<bucket>/<key>`, a path-style S3 URL
+/// - `http(s)://<host>[:<port>]/minio/<bucket>/<key>`, a link from the old minio browser
+/// - `http(s)://<host>[:<port>]/browser/<bucket>/<key>`, a link from the minio console
+pub fn parse_artifact_path(artifact_path: &str) -> Result<ArtifactLocation, Box<dyn Error>> {
+    if !artifact_path.contains("://") {
+        return Ok(ArtifactLocation {
+            endpoint: None,
+            bucket: None,
+            path: artifact_path.trim_start_matches('/').to_string(),
+        });
+    }
+    let url = Url::parse(artifact_path)
+        .map_err(|e| format!("Invalid artifact URL {}: {}", artifact_path, e))?;
+    if url.scheme() == "s3" {
+        let bucket = url
+            .host_str()
+            .ok_or_else(|| format!("No bucket in {}", artifact_path))?;
+        let segments: Vec<&str> = url.path().trim_start_matches('/').split('/').collect();
+        return Ok(ArtifactLocation {
+            endpoint: None,
+            bucket: Some(bucket.to_string()),
+            path: decoded_path(&segments)?,
+        });
+    }
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Unsupported artifact URL {}", artifact_path).into());
+    }
+
+    let endpoint = url.origin().ascii_serialization();
+    let segments: Vec<&str> = url.path().trim_start_matches('/').split('/').collect();
+    let (endpoint, segments) = match segments.as_slice() {
+        // The old minio browser is served by the S3 API itself
+        ["minio", rest @ ..] => (Some(endpoint), rest),
+        // The console runs on another port than the S3 API, so only the bucket is used
+        ["browser", rest @ ..] => (None, rest),
+        _ => (Some(endpoint), segments.as_slice()),
+    };
+    match segments {
+        [bucket, key @ ..] if !bucket.is_empty() => Ok(ArtifactLocation {
+            endpoint,
+            bucket: Some(decoded_path(&[bucket])?),
+            path: decoded_path(key)?,
+        }),
+        _ => Err(format!("No bucket in {}", artifact_path).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(endpoint: Option<&str>, bucket: Option<&str>, path: &str) -> ArtifactLocation {
+        ArtifactLocation {
+            endpoint: endpoint.map(str::to_string),
+            bucket: bucket.map(str::to_string),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn key_in_the_bucket() {
+        assert_eq!(
+            parse_artifact_path("/success/master/sdk/").unwrap(),
+            location(None, None, "success/master/sdk/")
+        );
+    }
+
+    #[test]
+    fn s3_url() {
+        assert_eq!(
+            parse_artifact_path("s3://arts/success/master/a.deb").unwrap(),
+            location(None, Some("arts"), "success/master/a.deb")
+        );
+    }
+
+    #[test]
+    fn path_style_url_with_port() {
+        assert_eq!(
+            parse_artifact_path("http://se-cluster-2:32000/arts/success/master/").unwrap(),
+            location(
+                Some("http://se-cluster-2:32000"),
+                Some("arts"),
+                "success/master/"
+            )
+        );
+    }
+
+    #[test]
+    fn minio_browser_url() {
+        assert_eq!(
+            parse_artifact_path("https://minio.example.com/minio/arts/success/").unwrap(),
+            location(Some("https://minio.example.com"), Some("arts"), "success/")
+        );
+    }
+
+    #[test]
+    fn minio_console_url_keeps_the_endpoint() {
+        assert_eq!(
+            parse_artifact_path("http://console:9001/browser/arts/success/master/").unwrap(),
+            location(None, Some("arts"), "success/master/")
+        );
+    }
+
+    #[test]
+    fn percent_encoded_key() {
+        assert_eq!(
+            parse_artifact_path("http://host:9000/arts/misc/rapport%20%C3%A6%2B1.txt").unwrap(),
+            location(
+                Some("http://host:9000"),
+                Some("arts"),
+                "misc/rapport æ+1.txt"
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_urls_are_refused() {
+        for artifact_path in [
+            "ftp://host/arts/key",
+            "http://host:9000/",
+            "http://host:9000/minio/",
+            "http://host:9000/arts/%FF",
+            "s3:///key",
+            "http://ho st/arts",
+        ] {
+            assert!(
+                parse_artifact_path(artifact_path).is_err(),
+                "{}",
+                artifact_path
+            );
+        }
+    }
+}
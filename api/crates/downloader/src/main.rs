@@ -1,7 +1,12 @@
+mod artifact_url;
+
+use artifact_url::{parse_artifact_path, ArtifactLocation};
+
 use buckets::download_artifacts_sync;
-use buckets::list_all_objects;
+use buckets::presign_folder;
 use buckets::print_flat_list;
 use buckets::print_tree_list;
+use buckets::S3Client;
 
 use clap::Parser;
 use clap::Subcommand;
@@ -28,6 +33,12 @@ enum Commands {
         #[command(subcommand)]
         format: Option<ListFormat>,
     },
+    #[command(about = "Print links to download all files given a minio path without credentials")]
+    Share {
+        /// How long the links are valid, at most a week
+        #[arg(short, long, default_value = "86400")]
+        expiry_secs: u32,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -36,49 +47,80 @@ enum Commands {
     about = "Download/list all files given a minio path. Default action is to download all artifacts preserving the folder hierarchy."
 )]
 struct Args {
-    /// The path to the artifact in minio
-    artifact_path: String,
+    /// The path to the artifact in minio, as a key in the bucket or as an s3://, path-style S3,
+    /// minio browser or minio console URL
+    #[arg(required_unless_present = "list_buckets")]
+    artifact_path: Option<String>,
+
+    /// Bucket of the artifact path instead of S3_BUCKET, the same as a URL naming one
+    #[arg(long)]
+    bucket: Option<String>,
+
+    /// List the buckets the credentials can see instead
+    #[arg(long, exclusive = true)]
+    list_buckets: bool,
 
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
-fn print_files(prefix: &str, format: ListFormat) -> Result<(), Box<dyn Error>> {
-    let objects = list_all_objects(&prefix)?;
+fn print_files(client: &S3Client, prefix: &str, format: ListFormat) -> Result<(), Box<dyn Error>> {
+    let objects = client.list_all_objects_sync(prefix)?;
     println!("Files in {}:", &prefix);
     match format {
         ListFormat::Flat => {
-            print_flat_list(&prefix, objects);
+            print_flat_list(prefix, objects);
         }
         ListFormat::Tree => {
-            print_tree_list(&prefix, objects);
+            print_tree_list(prefix, objects);
         }
     }
     Ok(())
 }
 
-fn strip_artifact_path_url(url: &str) -> String {
-    const SE_CLUSTER: &'static str = "http://se-cluster-2";
-    const SE_CLUSTER_ENDPOINT: &'static str = ":32000/se-ci-artifacts/";
-    const SE_CI_STORAGE: &'static str = "http://se-ci-storage";
-    const SE_CI_STORAGE_ENDPOINT: &'static str = ":9000/minio/se-ci-artifacts/";
-
-    let urls_to_strip = [
-        format!("{}{}", SE_CLUSTER, SE_CLUSTER_ENDPOINT),
-        format!("{}.localdomain{}", SE_CLUSTER, SE_CLUSTER_ENDPOINT),
-        format!("{}{}", SE_CI_STORAGE, SE_CI_STORAGE_ENDPOINT),
-        format!("{}.localdomain{}", SE_CI_STORAGE, SE_CI_STORAGE_ENDPOINT),
-    ];
-    let found_url = urls_to_strip.iter().find(|u| url.starts_with(*u));
-    if let Some(url_to_strip) = found_url {
-        return url.replace(url_to_strip, "");
+fn print_share_links(
+    client: &S3Client,
+    prefix: &str,
+    expiry_secs: u32,
+) -> Result<(), Box<dyn Error>> {
+    for (file_path_from_prefix, url) in presign_folder(client, prefix, expiry_secs)? {
+        println!("{}\t{}", file_path_from_prefix, url);
     }
-    url.to_string()
+    Ok(())
+}
+
+/// Client for the endpoint and bucket named in the artifact path, if any. A bucket given with
+/// `--bucket` must be the same as the one in the path.
+fn client_for(
+    location: &ArtifactLocation,
+    bucket: Option<&str>,
+) -> Result<S3Client, Box<dyn Error>> {
+    let bucket = match (location.bucket.as_deref(), bucket) {
+        (Some(url_bucket), Some(bucket)) if url_bucket != bucket => {
+            return Err(format!(
+                "The artifact path is in bucket {}, but --bucket is {}",
+                url_bucket, bucket
+            )
+            .into());
+        }
+        (url_bucket, bucket) => url_bucket.or(bucket),
+    };
+    S3Client::from_env_for(location.endpoint.as_deref(), bucket)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Show retried S3 calls
+    cli::init_tracing();
     let args = Args::parse();
-    let artifact_path = strip_artifact_path_url(&args.artifact_path);
+    if args.list_buckets {
+        for bucket in S3Client::from_env()?.list_buckets_sync()? {
+            println!("{}", bucket);
+        }
+        return Ok(());
+    }
+    let location = parse_artifact_path(&args.artifact_path.unwrap())?;
+    let client = client_for(&location, args.bucket.as_deref())?;
+    let artifact_path = location.path;
     let command = match args.command {
         Some(c) => c,
         None => Commands::Download {
@@ -87,14 +129,17 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
     match command {
         Commands::Download { destination_folder } => {
-            download_artifacts_sync(&artifact_path, &destination_folder)?;
+            download_artifacts_sync(&client, &artifact_path, &destination_folder)?;
         }
         Commands::List { format } => {
             let format = match format {
                 Some(f) => f,
                 None => ListFormat::Flat,
             };
-            return print_files(&artifact_path, format);
+            return print_files(&client, &artifact_path, format);
+        }
+        Commands::Share { expiry_secs } => {
+            return print_share_links(&client, &artifact_path, expiry_secs);
         }
     }
     Ok(())
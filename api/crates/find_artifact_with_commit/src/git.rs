@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+/// Full hashes of a commit and its ancestors, newest first, as listed by `git rev-list`.
+pub fn ancestors(
+    repository: &Path,
+    git_ref: &str,
+    max_count: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .arg("rev-list")
+        .arg(format!("--max-count={}", max_count))
+        .arg("--end-of-options")
+        .arg(git_ref)
+        .arg("--")
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git rev-list {} failed: {}",
+            git_ref,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
@@ -1,27 +1,233 @@
-use clap::Parser;
+mod git;
+
+use clap::{ArgGroup, Parser, Subcommand};
 use std::error::Error;
+use std::path::PathBuf;
+
+use buckets::{build_commit_index, list_commit_folders};
+use buckets::{lookup_artifact_with_commit_hash, lookup_commit_artifacts_in_all_branches};
+use buckets::{CommitArtifact, CommitHashError, CommitIndex};
+use buckets::{IndexLocation, S3Client};
 
-use buckets::find_artifact_with_commit_hash;
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Build the commit index from the bucket and save it to --index-file or --index-key
+    Reindex,
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about = "Retrieve the artifacts path from the commit hash", long_about = None)]
+#[command(group(ArgGroup::new("search").required(true).args(["release", "all_branches"])))]
+#[command(group(ArgGroup::new("commit").required(true).args(["commit_hash", "git_ref"])))]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Search the sdk release with this version, e.g. 2.13
+    #[clap(short, long)]
+    release: Option<String>,
+
+    /// Search master, every release branch and every other branch, printing the branch,
+    /// timestamp and path of every match
+    #[clap(short, long)]
+    all_branches: bool,
+
+    /// Commit hash, abbreviated to at least 4 digits or full
     #[clap(short, long)]
-    release: String,
+    commit_hash: Option<String>,
 
+    /// Find the artifacts of a git ref such as HEAD, or of its nearest ancestor that has any
     #[clap(short, long)]
-    commit_hash: String,
+    git_ref: Option<String>,
+
+    /// Local git repository to resolve --git-ref in
+    #[clap(long, default_value = ".")]
+    repository: PathBuf,
+
+    /// Number of commits from --git-ref on to look for artifacts
+    #[clap(long, default_value = "100")]
+    max_commits: usize,
+
+    /// Bucket to look in instead of S3_BUCKET
+    #[clap(long, global = true)]
+    bucket: Option<String>,
+
+    /// Commit index file to look up commits in before scanning the bucket
+    #[clap(long, global = true, conflicts_with = "index_key")]
+    index_file: Option<PathBuf>,
+
+    /// Key of the commit index in the bucket, instead of a local file
+    #[clap(long, global = true)]
+    index_key: Option<String>,
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
-    let release = args.release;
-    let artifact_path = find_artifact_with_commit_hash(
-        format!("success/release/release-sdk-{}/sdk/commit/", release).as_str(),
-        args.commit_hash.as_str(),
+fn index_location(args: &Args) -> Option<IndexLocation> {
+    if let Some(path) = &args.index_file {
+        return Some(IndexLocation::File(path.clone()));
+    }
+    args.index_key.clone().map(IndexLocation::Bucket)
+}
+
+async fn reindex(client: &S3Client, location: Option<IndexLocation>) -> Result<(), Box<dyn Error>> {
+    let location = location.ok_or("Give the index to build with --index-file or --index-key")?;
+    let index = build_commit_index(client).await?;
+    index.save(client, &location).await?;
+    println!("Indexed {} commit folders", index.len());
+    Ok(())
+}
+
+/// Line printed for an artifact found in every branch.
+fn artifact_line(artifact: &CommitArtifact) -> String {
+    format!(
+        "{}\t{}\t{}",
+        artifact.branch, artifact.timestamp, artifact.path
     )
-    .await?;
-    println!("{}", artifact_path);
+}
+
+/// Whether a lookup failed only because the commit has no artifacts.
+fn is_not_found(error: &(dyn Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<CommitHashError>(),
+        Some(CommitHashError::NotFound(_))
+    )
+}
+
+/// Walk the history from a git ref until a commit with artifacts is found, in the release
+/// branch `release_branch` or in every branch. The commit folders are listed once, and the
+/// commits matched against them newest first.
+async fn find_nearest_ancestor(
+    client: &S3Client,
+    args: &Args,
+    git_ref: &str,
+    index: Option<&CommitIndex>,
+    release_branch: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let commits = git::ancestors(&args.repository, git_ref, args.max_commits)?;
+    let commit_folders = list_commit_folders(client, index, release_branch).await?;
+    for (distance, commit_hash) in commits.iter().enumerate() {
+        let artifacts = match commit_folders.lookup(commit_hash) {
+            Ok(artifacts) => artifacts,
+            Err(e) if is_not_found(e.as_ref()) => continue,
+            Err(e) => return Err(e),
+        };
+        if distance > 0 {
+            eprintln!(
+                "{} has no artifacts, using its ancestor {} from {} commits before",
+                git_ref, commit_hash, distance
+            );
+        }
+        match release_branch {
+            // Like --commit-hash, only the newest build in a release
+            Some(_) => println!("{}", artifacts[0].path),
+            None => {
+                for artifact in &artifacts {
+                    println!("{}", artifact_line(artifact));
+                }
+            }
+        }
+        return Ok(());
+    }
+    Err(format!(
+        "None of the {} newest commits of {} have artifacts",
+        commits.len(),
+        git_ref
+    )
+    .into())
+}
+
+/// Client for the bucket of `--bucket`, or of `S3_BUCKET` when not given.
+fn client_for(args: &Args) -> Result<S3Client, Box<dyn Error>> {
+    S3Client::from_env_for(None, args.bucket.as_deref())
+}
+
+async fn find(args: Args) -> Result<(), Box<dyn Error>> {
+    let client = client_for(&args)?;
+    let location = index_location(&args);
+    if let Some(Command::Reindex) = args.command {
+        return reindex(&client, location).await;
+    }
+
+    // A missing or broken index only makes the lookup slower
+    let index = match &location {
+        Some(location) => match CommitIndex::load(&client, location).await {
+            Ok(index) => Some(index),
+            Err(e) => {
+                eprintln!("Scanning the bucket, the commit index can't be used: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+    if let Some(git_ref) = &args.git_ref {
+        let release_branch = args
+            .release
+            .as_ref()
+            .map(|release| format!("release-sdk-{}", release));
+        return find_nearest_ancestor(
+            &client,
+            &args,
+            git_ref,
+            index.as_ref(),
+            release_branch.as_deref(),
+        )
+        .await;
+    }
+
+    let commit_hash = args.commit_hash.unwrap();
+    if let Some(release) = args.release {
+        let artifact_path = lookup_artifact_with_commit_hash(
+            &client,
+            index.as_ref(),
+            format!("success/release/release-sdk-{}/sdk/commit/", release).as_str(),
+            commit_hash.as_str(),
+        )
+        .await?;
+        println!("{}", artifact_path);
+        return Ok(());
+    }
+
+    let artifacts =
+        lookup_commit_artifacts_in_all_branches(&client, index.as_ref(), &commit_hash).await?;
+    for artifact in &artifacts {
+        println!("{}", artifact_line(artifact));
+    }
     Ok(())
 }
+
+#[tokio::main]
+async fn main() {
+    // Show retried S3 calls
+    cli::init_tracing();
+    let args = Args::parse();
+    // Print errors with Display, so that ambiguous matches are listed one per line
+    if let Err(e) = find(args).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_option_is_enough() {
+        std::env::set_var("S3_HOSTNAME", "127.0.0.1:9000");
+        std::env::set_var("S3_ACCESSKEY", "access");
+        std::env::set_var("S3_SECRETKEY", "secret");
+        std::env::remove_var("S3_BUCKET");
+        let args = Args::parse_from([
+            "find_artifact_with_commit",
+            "--bucket",
+            "other",
+            "-a",
+            "-c",
+            "2bfb",
+        ]);
+        let client = client_for(&args).unwrap();
+        assert_eq!(client.bucket_name(), "other");
+        // Asking for S3_BUCKET would have set it
+        assert!(std::env::var("S3_BUCKET").is_err());
+    }
+}
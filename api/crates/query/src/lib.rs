@@ -0,0 +1,79 @@
+use glob::{MatchOptions, Pattern};
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Check whether a query is a glob pattern rather than a fragment of a path.
+pub fn is_glob(query: &str) -> bool {
+    query.contains(['*', '?', '['])
+}
+
+/// Check whether a file path relative to the searched prefix matches a query.
+///
+/// Queries containing `*`, `?` or `[` are treated as glob patterns and matched against the
+/// file name, or against the whole relative path when the query contains a `/`. Any other
+/// query matches paths containing it. Matching ignores case.
+pub fn matches_query(relative_path: &str, query: &str) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+    if !is_glob(query) {
+        return relative_path
+            .to_lowercase()
+            .contains(query.to_lowercase().as_str());
+    }
+    let pattern = match Pattern::new(query) {
+        Ok(pattern) => pattern,
+        Err(_) => return false,
+    };
+    let candidate = if query.contains('/') {
+        relative_path
+    } else {
+        relative_path.rsplit('/').next().unwrap()
+    };
+    pattern.matches_with(candidate, MATCH_OPTIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "ubuntu-20.04/amd64/Release/Zivid-SDK.deb";
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert!(matches_query(PATH, ""));
+    }
+
+    #[test]
+    fn fragment_matches_anywhere_ignoring_case() {
+        assert!(matches_query(PATH, "amd64/release"));
+        assert!(matches_query(PATH, "sdk.DEB"));
+        assert!(!matches_query(PATH, "arm64"));
+    }
+
+    #[test]
+    fn glob_matches_the_file_name() {
+        assert!(is_glob("*.deb"));
+        assert!(matches_query(PATH, "*.deb"));
+        assert!(matches_query(PATH, "zivid-sdk.de?"));
+        assert!(matches_query(PATH, "[xyz]ivid*"));
+        // Not the folders
+        assert!(!matches_query(PATH, "ubuntu*"));
+    }
+
+    #[test]
+    fn glob_with_slash_matches_the_relative_path() {
+        assert!(matches_query(PATH, "ubuntu-*/*/Release/*.deb"));
+        // `*` doesn't cross folders
+        assert!(!matches_query(PATH, "ubuntu-*/*.deb"));
+    }
+
+    #[test]
+    fn invalid_glob_matches_nothing() {
+        assert!(!matches_query(PATH, "[deb"));
+    }
+}
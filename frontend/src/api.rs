@@ -0,0 +1,132 @@
+use gloo_net::http::{Request, Response};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// Path of the API, served by the `browser` binary on the same origin as the frontend.
+pub const API_URL: &str = "/api";
+
+/// Query parameter selecting the bucket in links, empty for the default one.
+fn bucket_query(bucket: Option<&str>) -> String {
+    match bucket {
+        Some(bucket) => format!("&bucket={}", js_sys::encode_uri_component(bucket)),
+        None => String::new(),
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ArtifactEntry {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: String,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct PlatformFolder {
+    pub os: String,
+    pub architecture: String,
+    pub path: String,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct LatestBuild {
+    pub branch: String,
+    pub timestamp: String,
+    pub commit_hash: String,
+    pub path: String,
+    pub platforms: Vec<PlatformFolder>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ShareLink {
+    pub url: String,
+    pub expiry_secs: u32,
+}
+
+#[derive(Deserialize)]
+struct ErrorMessage {
+    error: String,
+}
+
+async fn error_message(response: Response) -> String {
+    match response.json::<ErrorMessage>().await {
+        Ok(message) => message.error,
+        Err(_) => response.status_text(),
+    }
+}
+
+/// Get `path` of the API from `bucket`, the default one of the server if `None`.
+async fn get_json<T: DeserializeOwned>(
+    path: &str,
+    bucket: Option<&str>,
+    params: &[(&str, &str)],
+) -> Result<T, String> {
+    let bucket_param = bucket.map(|bucket| ("bucket", bucket));
+    let response = Request::get(&format!("{}{}", API_URL, path))
+        .query(params.iter().copied().chain(bucket_param))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.ok() {
+        return Err(error_message(response).await);
+    }
+    response.json::<T>().await.map_err(|e| e.to_string())
+}
+
+/// Buckets the user can read.
+pub async fn buckets() -> Result<Vec<String>, String> {
+    get_json("/buckets", None, &[]).await
+}
+
+/// List every file below `prefix`.
+pub async fn list(bucket: Option<&str>, prefix: &str) -> Result<Vec<ArtifactEntry>, String> {
+    get_json("/list", bucket, &[("prefix", prefix)]).await
+}
+
+/// Search every file below `prefix` for a name fragment or glob.
+pub async fn search(
+    bucket: Option<&str>,
+    prefix: &str,
+    query: &str,
+) -> Result<Vec<ArtifactEntry>, String> {
+    get_json("/search", bucket, &[("prefix", prefix), ("q", query)]).await
+}
+
+/// Newest successful commit of every branch.
+pub async fn latest(bucket: Option<&str>) -> Result<Vec<LatestBuild>, String> {
+    get_json("/latest", bucket, &[]).await
+}
+
+/// Presigned link to download a file without credentials.
+pub async fn share(bucket: Option<&str>, key: &str) -> Result<ShareLink, String> {
+    get_json("/share", bucket, &[("key", key)]).await
+}
+
+/// Link downloading a single file.
+pub fn download_url(bucket: Option<&str>, key: &str) -> String {
+    let key = String::from(js_sys::encode_uri_component(key));
+    format!("{}/download?key={}{}", API_URL, key, bucket_query(bucket))
+}
+
+/// Link to a single file served with its content type, e.g. for `<img>`.
+pub fn object_url(bucket: Option<&str>, key: &str) -> String {
+    let key = String::from(js_sys::encode_uri_component(key));
+    format!("{}/object?key={}{}", API_URL, key, bucket_query(bucket))
+}
+
+/// Read bytes `start` to `end` (inclusive) of a file.
+pub async fn object_range(
+    bucket: Option<&str>,
+    key: &str,
+    start: u64,
+    end: u64,
+) -> Result<Vec<u8>, String> {
+    let response = Request::get(&object_url(bucket, key))
+        .header("Range", &format!("bytes={}-{}", start, end))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.ok() {
+        return Err(error_message(response).await);
+    }
+    response.binary().await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,124 @@
+use crate::api::{self, ArtifactEntry};
+use crate::preview::Preview;
+
+use leptos::*;
+
+use query::matches_query;
+
+/// Load the files under a prefix and filter them by name fragment or glob.
+///
+/// Filtering happens in the browser on the loaded listing. "Search prefix" asks the server
+/// to search everything under the prefix instead, e.g. a whole branch. The listing is cleared
+/// when another bucket is picked.
+#[component]
+pub fn ArtifactBrowser(bucket: ReadSignal<Option<String>>) -> impl IntoView {
+    let (prefix, set_prefix) = create_signal(String::new());
+    let (filter, set_filter) = create_signal(String::new());
+    let (loaded_prefix, set_loaded_prefix) = create_signal(String::new());
+    // Bucket the listed files are in, another one may have been picked since
+    let (loaded_bucket, set_loaded_bucket) = create_signal(None::<String>);
+    let (entries, set_entries) = create_signal(Vec::<ArtifactEntry>::new());
+    let (status, set_status) = create_signal(None::<String>);
+    let (selected, set_selected) = create_signal(None::<ArtifactEntry>);
+
+    create_effect(move |previous: Option<Option<String>>| {
+        let current = bucket.get();
+        if previous.is_some_and(|previous| previous != current) {
+            set_entries.set(Vec::new());
+            set_selected.set(None);
+            set_status.set(None);
+        }
+        current
+    });
+
+    let show_result = move |bucket: Option<String>,
+                            prefix: String,
+                            result: Result<Vec<_>, String>| match result {
+        Ok(found) => {
+            set_status.set(Some(format!("{} files", found.len())));
+            set_entries.set(found);
+            set_loaded_prefix.set(prefix);
+            set_loaded_bucket.set(bucket);
+        }
+        Err(e) => set_status.set(Some(format!("Error: {}", e))),
+    };
+
+    let load = move |_| {
+        let bucket = bucket.get();
+        let prefix = prefix.get();
+        set_status.set(Some(format!("Loading {}...", prefix)));
+        spawn_local(async move {
+            let result = api::list(bucket.as_deref(), &prefix).await;
+            show_result(bucket, prefix, result);
+        });
+    };
+
+    let search = move |_| {
+        let bucket = bucket.get();
+        let prefix = prefix.get();
+        let query = filter.get();
+        set_status.set(Some(format!("Searching {} for '{}'...", prefix, query)));
+        spawn_local(async move {
+            let result = api::search(bucket.as_deref(), &prefix, &query).await;
+            show_result(bucket, prefix, result);
+        });
+    };
+
+    let visible_entries = move || {
+        let query = filter.get();
+        let prefix = loaded_prefix.get();
+        entries
+            .get()
+            .into_iter()
+            .filter(|entry| {
+                let relative_path = entry.key.strip_prefix(&prefix).unwrap_or(&entry.key);
+                matches_query(relative_path, &query)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    view! {
+        <div>
+            <input
+                type="text"
+                placeholder="Prefix, e.g. success/master/"
+                prop:value=prefix
+                on:input=move |ev| set_prefix.set(event_target_value(&ev))
+            />
+            <button on:click=load>"Load"</button>
+        </div>
+        <div>
+            <input
+                type="text"
+                placeholder="Filter, e.g. *.deb"
+                prop:value=filter
+                on:input=move |ev| set_filter.set(event_target_value(&ev))
+            />
+            <button on:click=search>"Search prefix"</button>
+        </div>
+        <p>{move || status.get()}</p>
+        <ul>
+            <For
+                each=visible_entries
+                key=|entry| entry.key.clone()
+                children=move |entry| {
+                    let key = entry.key.clone();
+                    let size = entry.size;
+                    let select = move |ev: ev::MouseEvent| {
+                        ev.prevent_default();
+                        set_selected.set(Some(entry.clone()));
+                    };
+                    view! {
+                        <li>
+                            <a href="#" on:click=select>{key}</a>
+                            " ("
+                            {size}
+                            " bytes)"
+                        </li>
+                    }
+                }
+            />
+        </ul>
+        <Preview entry=selected bucket=loaded_bucket/>
+    }
+}
@@ -0,0 +1,45 @@
+use crate::api;
+
+use leptos::*;
+
+/// Pick the bucket the other components read from, `None` for the server's default bucket.
+#[component]
+pub fn BucketSelect(set_bucket: WriteSignal<Option<String>>) -> impl IntoView {
+    let buckets = create_local_resource(|| (), |_| async move { api::buckets().await });
+
+    let select = move |ev| {
+        let value = event_target_value(&ev);
+        let bucket = (!value.is_empty()).then_some(value);
+        set_bucket.set(bucket);
+    };
+
+    view! {
+        <Suspense fallback=|| ()>
+            {move || {
+                buckets
+                    .get()
+                    .map(|result| match result {
+                        Ok(buckets) => {
+                            view! {
+                                <label>
+                                    "Bucket "
+                                    <select on:change=select>
+                                        <option value="">"Default"</option>
+                                        {buckets
+                                            .into_iter()
+                                            .map(|bucket| {
+                                                view! { <option value=bucket.clone()>{bucket}</option> }
+                                            })
+                                            .collect_view()}
+                                    </select>
+                                </label>
+                            }
+                                .into_view()
+                        }
+                        // Other buckets are only unavailable, the default one still works
+                        Err(e) => view! { <p>"Can't list buckets: " {e}</p> }.into_view(),
+                    })
+            }}
+        </Suspense>
+    }
+}
@@ -0,0 +1,106 @@
+use crate::api::{self, ArtifactEntry, LatestBuild};
+
+use leptos::*;
+
+#[component]
+fn BuildRow(latest: LatestBuild, bucket: Option<String>) -> impl IntoView {
+    let bucket = store_value(bucket);
+    let (files, set_files) = create_signal(Vec::<ArtifactEntry>::new());
+    let (status, set_status) = create_signal(None::<String>);
+
+    let platforms = latest
+        .platforms
+        .into_iter()
+        .map(|platform| {
+            let label = format!("{} {}", platform.os, platform.architecture);
+            let show_files = move |_| {
+                let path = platform.path.clone();
+                set_status.set(Some(format!("Loading {}...", path)));
+                spawn_local(async move {
+                    match api::list(bucket.get_value().as_deref(), &path).await {
+                        Ok(found) => {
+                            set_status.set(None);
+                            set_files.set(found);
+                        }
+                        Err(e) => set_status.set(Some(format!("Error: {}", e))),
+                    }
+                });
+            };
+            view! { <button on:click=show_files>{label}</button> }
+        })
+        .collect_view();
+
+    view! {
+        <tr>
+            <td>{latest.branch}</td>
+            <td>{latest.timestamp}</td>
+            <td>{latest.commit_hash}</td>
+            <td>
+                {platforms}
+                <p>{move || status.get()}</p>
+                <ul>
+                    {move || {
+                        files
+                            .get()
+                            .into_iter()
+                            .map(|file| {
+                                let file_name = file.key.rsplit('/').next().unwrap().to_string();
+                                let url = api::download_url(bucket.get_value().as_deref(), &file.key);
+                                view! {
+                                    <li>
+                                        <a href=url>{file_name}</a>
+                                    </li>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </ul>
+            </td>
+        </tr>
+    }
+}
+
+/// Latest successful commit of every branch with links to its artifacts, reloaded when another
+/// bucket is picked.
+#[component]
+pub fn LatestBuilds(bucket: ReadSignal<Option<String>>) -> impl IntoView {
+    let builds = create_local_resource(
+        move || bucket.get(),
+        |bucket| async move {
+            let builds = api::latest(bucket.as_deref()).await;
+            (bucket, builds)
+        },
+    );
+
+    view! {
+        <h2>"Latest builds"</h2>
+        <Suspense fallback=|| view! { <p>"Loading..."</p> }>
+            {move || {
+                builds
+                    .get()
+                    .map(|(bucket, result)| match result {
+                        Ok(builds) => {
+                            view! {
+                                <table>
+                                    <tr>
+                                        <th>"Branch"</th>
+                                        <th>"Timestamp"</th>
+                                        <th>"Commit"</th>
+                                        <th>"Downloads"</th>
+                                    </tr>
+                                    {builds
+                                        .into_iter()
+                                        .map(|build| {
+                                            view! { <BuildRow latest=build bucket=bucket.clone()/> }
+                                        })
+                                        .collect_view()}
+                                </table>
+                            }
+                                .into_view()
+                        }
+                        Err(e) => view! { <p>"Error: " {e}</p> }.into_view(),
+                    })
+            }}
+        </Suspense>
+    }
+}
@@ -1,21 +1,9 @@
-use leptos::*;
+mod api;
+mod browser;
+mod bucket_select;
+mod dashboard;
+mod preview;
 
-/// A simple counter component.
-///
-/// You can use doc comments like this to document your component.
-#[component]
-pub fn SimpleCounter(
-    initial_value: i32,
-    step: i32,
-) -> impl IntoView {
-    let (value, set_value) = create_signal(initial_value);
-
-    view! {
-        <div>
-            <button on:click=move |_| set_value.set(0)>"Clear"</button>
-            <button on:click=move |_| set_value.update(|value| *value -= step)>"-1"</button>
-            <span>"Value: " {value} "!"</span>
-            <button on:click=move |_| set_value.update(|value| *value += step)>"+1"</button>
-        </div>
-    }
-}
\ No newline at end of file
+pub use crate::browser::ArtifactBrowser;
+pub use crate::bucket_select::BucketSelect;
+pub use crate::dashboard::LatestBuilds;
@@ -1,15 +1,15 @@
-use frontend::SimpleCounter;
+use frontend::{ArtifactBrowser, BucketSelect, LatestBuilds};
 use leptos::*;
 
 pub fn main() {
     _ = console_log::init_with_level(log::Level::Debug);
     console_error_panic_hook::set_once();
     mount_to_body(|| {
+        let (bucket, set_bucket) = create_signal(None::<String>);
         view! {
-            <SimpleCounter
-                initial_value=0
-                step=1
-            />
+            <BucketSelect set_bucket=set_bucket/>
+            <LatestBuilds bucket=bucket/>
+            <ArtifactBrowser bucket=bucket/>
         }
     })
-}
\ No newline at end of file
+}
@@ -0,0 +1,171 @@
+use crate::api::{self, ArtifactEntry};
+
+use leptos::*;
+
+/// Text is loaded from the end of the file in chunks of this size.
+const CHUNK_SIZE: u64 = 64 * 1024;
+/// JSON is only pretty printed when the whole file is loaded, so load it all up to this size.
+const JSON_SIZE_LIMIT: u64 = 4 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+enum PreviewKind {
+    Text,
+    Json,
+    Image,
+    Unsupported,
+}
+
+fn preview_kind(key: &str) -> PreviewKind {
+    let file_name = key.rsplit('/').next().unwrap();
+    let extension = match file_name.rsplit_once('.') {
+        Some((_, extension)) => extension.to_lowercase(),
+        // Logs are often uploaded without an extension
+        None => return PreviewKind::Text,
+    };
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" => PreviewKind::Image,
+        "json" => PreviewKind::Json,
+        "txt" | "log" | "md" | "csv" | "xml" | "yml" | "yaml" | "ini" | "cfg" | "toml" | "sh"
+        | "py" | "cmake" => PreviewKind::Text,
+        _ => PreviewKind::Unsupported,
+    }
+}
+
+fn number_lines(text: &str, first_line: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let width = (first_line + lines.len()).to_string().len();
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| format!("{:>width$} | {}\n", first_line + i, line, width = width))
+        .collect()
+}
+
+/// Show a text file with line numbers, starting from its end.
+///
+/// Large files are fetched a chunk at a time with range requests, so only the tail of a
+/// big log is downloaded unless earlier parts are asked for.
+#[component]
+fn TextPreview(entry: ArtifactEntry, bucket: Option<String>, json: bool) -> impl IntoView {
+    let size = entry.size;
+    let key = store_value(entry.key);
+    let bucket = store_value(bucket);
+    // Loaded bytes of the file, starting at `offset`
+    let (content, set_content) = create_signal(Vec::<u8>::new());
+    let (offset, set_offset) = create_signal(size);
+    let (status, set_status) = create_signal(None::<String>);
+
+    let load_before = move |end: u64| {
+        let chunk_size = if json { JSON_SIZE_LIMIT } else { CHUNK_SIZE };
+        let start = end.saturating_sub(chunk_size);
+        set_status.set(Some("Loading...".to_string()));
+        spawn_local(async move {
+            let bucket = bucket.get_value();
+            match api::object_range(bucket.as_deref(), &key.get_value(), start, end - 1).await {
+                Ok(mut chunk) => {
+                    set_content.update(|content| {
+                        chunk.append(content);
+                        *content = chunk;
+                    });
+                    set_offset.set(start);
+                    set_status.set(None);
+                }
+                Err(e) => set_status.set(Some(format!("Error: {}", e))),
+            }
+        });
+    };
+    if size > 0 {
+        load_before(size);
+    }
+
+    let text = move || {
+        let content = content.get();
+        let text = String::from_utf8_lossy(&content);
+        if offset.get() > 0 {
+            // Drop the line cut in half by the start of the chunk
+            let text = text.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+            return number_lines(text, 1);
+        }
+        if json {
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) {
+                return number_lines(&serde_json::to_string_pretty(&value).unwrap(), 1);
+            }
+        }
+        number_lines(&text, 1)
+    };
+
+    view! {
+        <Show when=move || { offset.get() > 0 && offset.get() < size }>
+            <p>
+                "Showing the last " {move || size - offset.get()} " of " {size}
+                " bytes, line numbers count from the first line shown. "
+                <button on:click=move |_| load_before(offset.get())>"Load earlier"</button>
+            </p>
+        </Show>
+        <p>{move || status.get()}</p>
+        <pre>{text}</pre>
+    }
+}
+
+/// Button copying a presigned link to the file, for people without access to the bucket.
+#[component]
+fn ShareButton(path: String, bucket: Option<String>) -> impl IntoView {
+    let path = store_value(path);
+    let bucket = store_value(bucket);
+    let (link, set_link) = create_signal(None::<String>);
+
+    let copy_link = move |_| {
+        spawn_local(async move {
+            match api::share(bucket.get_value().as_deref(), &path.get_value()).await {
+                Ok(link) => {
+                    // The link is shown as well, in case the clipboard isn't available
+                    let _ = window().navigator().clipboard().write_text(&link.url);
+                    let hours = link.expiry_secs / 3600;
+                    set_link.set(Some(format!(
+                        "Copied, valid for {} hours: {}",
+                        hours, link.url
+                    )));
+                }
+                Err(e) => set_link.set(Some(format!("Error: {}", e))),
+            }
+        });
+    };
+
+    view! {
+        <button on:click=copy_link>"Copy share link"</button>
+        <p>{move || link.get()}</p>
+    }
+}
+
+/// Preview of the selected file of `bucket`: text and logs, pretty printed JSON or images.
+#[component]
+pub fn Preview(
+    #[prop(into)] entry: Signal<Option<ArtifactEntry>>,
+    #[prop(into)] bucket: Signal<Option<String>>,
+) -> impl IntoView {
+    move || {
+        let bucket = bucket.get();
+        entry.get().map(|entry| {
+            let download_url = api::download_url(bucket.as_deref(), &entry.key);
+            let header = view! {
+                <h3>{entry.key.clone()} " "<a href=download_url>"Download"</a></h3>
+                <ShareButton path=entry.key.clone() bucket=bucket.clone()/>
+            };
+            let body = match preview_kind(&entry.key) {
+                PreviewKind::Image => {
+                    view! { <img src=api::object_url(bucket.as_deref(), &entry.key)/> }.into_view()
+                }
+                PreviewKind::Json => {
+                    view! { <TextPreview entry=entry bucket=bucket json=true/> }.into_view()
+                }
+                PreviewKind::Text => {
+                    view! { <TextPreview entry=entry bucket=bucket json=false/> }.into_view()
+                }
+                PreviewKind::Unsupported => {
+                    view! { <p>"No preview for this file type"</p> }.into_view()
+                }
+            };
+            view! { {header} {body} }
+        })
+    }
+}